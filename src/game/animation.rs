@@ -0,0 +1,308 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use bevy_rapier2d::dynamics::Velocity;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::AppState;
+
+use super::{butterfly::ButterflyEvent, BulletFiredEvent, Player, PlayerHitEvent};
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SpriteSheetDescription>()
+            .init_asset_loader::<SpriteSheetLoader>()
+            .add_systems(
+                Update,
+                (start_action_animations, animate_players)
+                    .chain()
+                    .after(super::player::player_controller)
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct SpriteAnimation {
+    pub first: usize,
+    pub last: usize,
+    // Seconds each frame is shown
+    pub frame_time: f32,
+}
+
+impl SpriteAnimation {
+    fn duration(&self) -> f32 {
+        (self.last - self.first + 1) as f32 * self.frame_time
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SpriteAnimations {
+    pub idle: SpriteAnimation,
+    pub run: SpriteAnimation,
+    pub jump: SpriteAnimation,
+    pub fall: SpriteAnimation,
+    pub shoot: SpriteAnimation,
+    pub hit: SpriteAnimation,
+    pub teleport: SpriteAnimation,
+}
+
+impl SpriteAnimations {
+    fn named(&self) -> [(&'static str, &SpriteAnimation); 7] {
+        [
+            ("idle", &self.idle),
+            ("run", &self.run),
+            ("jump", &self.jump),
+            ("fall", &self.fall),
+            ("shoot", &self.shoot),
+            ("hit", &self.hit),
+            ("teleport", &self.teleport),
+        ]
+    }
+
+    pub fn get(&self, state: AnimationState) -> &SpriteAnimation {
+        match state {
+            AnimationState::Idle => &self.idle,
+            AnimationState::Run => &self.run,
+            AnimationState::Jump => &self.jump,
+            AnimationState::Fall => &self.fall,
+            AnimationState::Shoot => &self.shoot,
+            AnimationState::Hit => &self.hit,
+            AnimationState::Teleport => &self.teleport,
+        }
+    }
+}
+
+// What a .sheet.ron file holds
+#[derive(Deserialize)]
+struct SpriteSheetFile {
+    texture: String,
+    tile_size: Vec2,
+    columns: usize,
+    rows: usize,
+    animations: SpriteAnimations,
+}
+
+// The texture atlas is also available on its own as "<path>#atlas"
+#[derive(Asset, TypePath)]
+pub struct SpriteSheetDescription {
+    pub atlas: Handle<TextureAtlas>,
+    pub animations: SpriteAnimations,
+}
+
+#[derive(Default)]
+pub struct SpriteSheetLoader;
+
+#[derive(Debug, Error)]
+pub enum SpriteSheetLoaderError {
+    #[error("Could not read sprite sheet file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse sprite sheet file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Animation {name} uses frames {first} to {last} of a sheet with {frames} frames")]
+    BadFrames {
+        name: &'static str,
+        first: usize,
+        last: usize,
+        frames: usize,
+    },
+    #[error("Animation {name} has frame time {frame_time}, it has to be positive")]
+    BadFrameTime { name: &'static str, frame_time: f32 },
+}
+
+impl AssetLoader for SpriteSheetLoader {
+    type Asset = SpriteSheetDescription;
+    type Settings = ();
+    type Error = SpriteSheetLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let file = ron::de::from_bytes::<SpriteSheetFile>(&bytes)?;
+
+            let frames = file.columns * file.rows;
+            for (name, animation) in file.animations.named() {
+                if animation.first > animation.last || animation.last >= frames {
+                    return Err(SpriteSheetLoaderError::BadFrames {
+                        name,
+                        first: animation.first,
+                        last: animation.last,
+                        frames,
+                    });
+                }
+                if animation.frame_time.is_nan() || animation.frame_time <= 0.0 {
+                    return Err(SpriteSheetLoaderError::BadFrameTime {
+                        name,
+                        frame_time: animation.frame_time,
+                    });
+                }
+            }
+
+            let texture = load_context.load(file.texture);
+            let atlas = TextureAtlas::from_grid(
+                texture,
+                file.tile_size,
+                file.columns,
+                file.rows,
+                None,
+                None,
+            );
+
+            Ok(SpriteSheetDescription {
+                atlas: load_context.add_labeled_asset("atlas".to_string(), atlas),
+                animations: file.animations,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sheet.ron"]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum AnimationState {
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    // Played once over the movement states, later ones win over earlier ones
+    Shoot,
+    Teleport,
+    Hit,
+}
+
+#[derive(Component)]
+pub struct PlayerAnimation {
+    sheet: Handle<SpriteSheetDescription>,
+    state: AnimationState,
+    frame_timer: Timer,
+    // One-off animation and how much of it is left
+    action: Option<(AnimationState, Timer)>,
+}
+
+impl PlayerAnimation {
+    pub fn new(sheet: Handle<SpriteSheetDescription>) -> Self {
+        Self {
+            sheet,
+            state: AnimationState::Idle,
+            frame_timer: Timer::default(),
+            action: None,
+        }
+    }
+
+    fn play_once(&mut self, state: AnimationState, animations: &SpriteAnimations) {
+        if self
+            .action
+            .as_ref()
+            .is_some_and(|(current, _)| *current > state)
+        {
+            return;
+        }
+        let duration = animations.get(state).duration();
+        self.action = Some((state, Timer::from_seconds(duration, TimerMode::Once)));
+    }
+}
+
+fn start_action_animations(
+    sheets: Res<Assets<SpriteSheetDescription>>,
+    mut players: Query<(Entity, &Player, &mut PlayerAnimation)>,
+    mut fired_events: EventReader<BulletFiredEvent>,
+    mut hit_events: EventReader<PlayerHitEvent>,
+    mut butterfly_events: EventReader<ButterflyEvent>,
+) {
+    let fired: Vec<i32> = fired_events.read().map(|event| event.player_id).collect();
+    let hit: Vec<Entity> = hit_events.read().map(|event| event.target).collect();
+    let teleported: Vec<i32> = butterfly_events
+        .read()
+        .map(|event| event.player_id)
+        .collect();
+
+    for (entity, player, mut animation) in &mut players {
+        let Some(sheet) = sheets.get(&animation.sheet) else {
+            continue;
+        };
+        let animations = &sheet.animations;
+
+        if fired.contains(&player.id) {
+            animation.play_once(AnimationState::Shoot, animations);
+        }
+        if teleported.contains(&player.id) {
+            animation.play_once(AnimationState::Teleport, animations);
+        }
+        if hit.contains(&entity) {
+            animation.play_once(AnimationState::Hit, animations);
+        }
+    }
+}
+
+fn movement_state(player: &Player, velocity_y: f32) -> AnimationState {
+    if !player.grounded {
+        if velocity_y > 0.0 {
+            AnimationState::Jump
+        } else {
+            AnimationState::Fall
+        }
+    } else if player.is_running {
+        AnimationState::Run
+    } else {
+        AnimationState::Idle
+    }
+}
+
+fn animate_players(
+    time: Res<Time>,
+    sheets: Res<Assets<SpriteSheetDescription>>,
+    mut players: Query<(
+        &Player,
+        &Velocity,
+        &mut PlayerAnimation,
+        &mut TextureAtlasSprite,
+    )>,
+) {
+    for (player, velocity, mut animation, mut sprite) in &mut players {
+        let Some(sheet) = sheets.get(&animation.sheet) else {
+            continue;
+        };
+
+        let action_done = animation
+            .action
+            .as_mut()
+            .is_some_and(|(_, timer)| timer.tick(time.delta()).finished());
+        if action_done {
+            animation.action = None;
+        }
+        let state = match &animation.action {
+            Some((action, _)) => *action,
+            None => movement_state(player, velocity.linvel.y),
+        };
+        let frames = sheet.animations.get(state);
+
+        if state != animation.state {
+            animation.state = state;
+            animation.frame_timer = Timer::from_seconds(frames.frame_time, TimerMode::Repeating);
+            sprite.index = frames.first;
+            continue;
+        }
+
+        animation.frame_timer.tick(time.delta());
+        if animation.frame_timer.just_finished() {
+            sprite.index = if sprite.index >= frames.last || sprite.index < frames.first {
+                frames.first
+            } else {
+                sprite.index + 1
+            };
+        }
+    }
+}
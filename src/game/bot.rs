@@ -0,0 +1,287 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::prelude::*;
+
+use crate::AppState;
+
+use super::{
+    butterfly::Butterfly, BotDifficulty, GameDirection, MapDescription, Platform, Player,
+    PlayerAction, PlayerInput, PlayerInputSet, PowerupState,
+};
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            drive_bots
+                .in_set(PlayerInputSet)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+// Roughly how far up and across a single jump carries a player
+const JUMP_HEIGHT: f32 = 6.0;
+const JUMP_DISTANCE: f32 = 8.0;
+// Bullets fly straight, so only shoot at targets about level with the bot
+const SHOT_HEIGHT_TOLERANCE: f32 = 0.8;
+const SHOT_RANGE: f32 = 30.0;
+// Don't walk right up to the target, it shoots back
+const KEEP_DISTANCE: f32 = 4.0;
+const MIRROR_RANGE: f32 = 8.0;
+// Start looking for a way back up when this close to the death zone
+const DANGER_HEIGHT: f32 = 15.0;
+
+// Fills in PlayerInput for a player, like a human on a controller would
+#[derive(Component)]
+pub struct BotController {
+    difficulty: BotDifficulty,
+    decision: Timer,
+    held: Vec<PlayerAction>,
+    mirror_hold: Option<Timer>,
+}
+
+impl BotController {
+    pub fn new(difficulty: BotDifficulty) -> Self {
+        Self {
+            difficulty,
+            decision: Timer::from_seconds(difficulty.reaction_time(), TimerMode::Repeating),
+            held: vec![],
+            mirror_hold: None,
+        }
+    }
+}
+
+impl BotDifficulty {
+    // Seconds between decisions, the bot keeps its last plan in between
+    fn reaction_time(self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.4,
+            BotDifficulty::Normal => 0.2,
+            BotDifficulty::Hard => 0.08,
+        }
+    }
+
+    // Chance to take a shot that is lined up
+    fn accuracy(self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.25,
+            BotDifficulty::Normal => 0.6,
+            BotDifficulty::Hard => 0.95,
+        }
+    }
+
+    // How far from a platform edge the bot stops walking
+    fn edge_margin(self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.3,
+            BotDifficulty::Normal => 0.8,
+            BotDifficulty::Hard => 1.2,
+        }
+    }
+
+    fn uses_powerups(self) -> bool {
+        self != BotDifficulty::Easy
+    }
+}
+
+// Top of a platform's bounding box, which is all the bot knows about its shape
+#[derive(Clone, Copy)]
+struct Surface {
+    left: f32,
+    right: f32,
+    top: f32,
+}
+
+impl Surface {
+    fn covers(&self, x: f32) -> bool {
+        self.left <= x && x <= self.right
+    }
+
+    fn center(&self) -> f32 {
+        (self.left + self.right) / 2.0
+    }
+
+    fn horizontal_distance(&self, x: f32) -> f32 {
+        (self.left - x).max(x - self.right).max(0.0)
+    }
+}
+
+fn ground_below(surfaces: &[Surface], position: Vec2) -> Option<f32> {
+    surfaces
+        .iter()
+        .filter(|surface| surface.covers(position.x) && surface.top <= position.y + 0.5)
+        .map(|surface| surface.top)
+        .reduce(f32::max)
+}
+
+fn direction_action(dx: f32) -> PlayerAction {
+    if dx < 0.0 {
+        PlayerAction::Left
+    } else {
+        PlayerAction::Right
+    }
+}
+
+fn drive_bots(
+    time: Res<Time>,
+    map: Res<MapDescription>,
+    mut bots: Query<(
+        Entity,
+        &Transform,
+        &Velocity,
+        &Player,
+        &mut BotController,
+        &mut PlayerInput,
+    )>,
+    players: Query<(Entity, &Transform, &Player)>,
+    platforms: Query<(&Platform, &Transform)>,
+    butterflies: Query<&Transform, With<Butterfly>>,
+) {
+    let surfaces: Vec<Surface> = platforms
+        .iter()
+        .map(|(platform, transform)| {
+            let (min, max) = platform
+                .get_transformed_contour(transform)
+                .flatten(platform.flatten_tolerance)
+                .bounding_box();
+            Surface {
+                left: min.x,
+                right: max.x,
+                top: max.y,
+            }
+        })
+        .collect();
+    let mut rng = thread_rng();
+
+    for (entity, transform, velocity, player, mut bot, mut input) in &mut bots {
+        let position = transform.translation.xy();
+        // Teammates are left alone
+        let target = players
+            .iter()
+            .filter(|(other, _, other_player)| *other != entity && other_player.team != player.team)
+            .map(|(_, transform, _)| transform.translation.xy())
+            .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+
+        bot.decision.tick(time.delta());
+        if bot.decision.just_finished() {
+            let mut actions = vec![];
+            let mut movement = None;
+
+            if ground_below(&surfaces, position).is_none() {
+                // Head for the closest platform that can still be reached
+                if let Some(surface) = surfaces
+                    .iter()
+                    .filter(|surface| surface.top < position.y + JUMP_HEIGHT)
+                    .min_by(|a, b| {
+                        a.horizontal_distance(position.x)
+                            .total_cmp(&b.horizontal_distance(position.x))
+                    })
+                {
+                    movement = Some(direction_action(surface.center() - position.x));
+                }
+                actions.push(PlayerAction::Jump);
+
+                // The butterfly reflects the player through it, which only helps when it is above
+                let falling = velocity.linvel.y < 0.0;
+                let in_danger = position.y - map.death_zone < DANGER_HEIGHT;
+                if falling
+                    && in_danger
+                    && butterflies
+                        .iter()
+                        .any(|butterfly| butterfly.translation.y > position.y)
+                {
+                    actions.push(PlayerAction::Butterfly);
+                }
+            } else if let Some(target) = target {
+                let delta = target - position;
+                let towards = direction_action(delta.x);
+                let sign = delta.x.signum();
+
+                if delta.x.abs() > KEEP_DISTANCE || delta.y.abs() > SHOT_HEIGHT_TOLERANCE {
+                    let ahead =
+                        Vec2::new(position.x + sign * bot.difficulty.edge_margin(), position.y);
+                    let landing = surfaces.iter().any(|surface| {
+                        surface.top < position.y + JUMP_HEIGHT
+                            && surface.horizontal_distance(position.x) < JUMP_DISTANCE
+                            && (surface.center() - position.x) * sign > 0.0
+                            && !surface.covers(position.x)
+                    });
+
+                    if ground_below(&surfaces, ahead).is_some() {
+                        movement = Some(towards);
+                    } else if landing {
+                        movement = Some(towards);
+                        actions.push(PlayerAction::Jump);
+                    }
+                }
+
+                // Climb towards targets that are standing higher up
+                let step_up = surfaces.iter().find(|surface| {
+                    surface.top > position.y + 1.0
+                        && surface.top < position.y + JUMP_HEIGHT
+                        && surface.horizontal_distance(position.x) < JUMP_DISTANCE / 2.0
+                });
+                if let Some(surface) = step_up.filter(|_| delta.y > 2.0) {
+                    movement = Some(direction_action(surface.center() - position.x));
+                    actions.push(PlayerAction::Jump);
+                }
+
+                let lined_up = delta.y.abs() < SHOT_HEIGHT_TOLERANCE && delta.x.abs() < SHOT_RANGE;
+                if lined_up && rng.gen::<f32>() < bot.difficulty.accuracy() {
+                    let facing = match player.facing_direction {
+                        GameDirection::Left => PlayerAction::Left,
+                        GameDirection::Right => PlayerAction::Right,
+                    };
+                    if facing != towards {
+                        movement = Some(towards);
+                    }
+                    actions.push(PlayerAction::Shoot);
+                }
+            }
+
+            // Jumps only go off on a fresh press and get cut short on release, so hold
+            // jump through the rise and let go on the way down
+            let rising = player.is_jumping && velocity.linvel.y > 0.0;
+            if rising && bot.held.contains(&PlayerAction::Jump) {
+                actions.push(PlayerAction::Jump);
+            } else if !rising && !player.grounded {
+                actions.retain(|action| *action != PlayerAction::Jump);
+            }
+
+            actions.extend(movement);
+            bot.held = actions;
+        }
+
+        for action in &bot.held {
+            input.press(*action);
+        }
+
+        // Place a mirror next to the target, then set it off by letting go
+        let unplaced_mirror = matches!(
+            player.powerup,
+            Some(PowerupState::Mirror { placed: false, .. })
+        );
+        let target_close = target.is_some_and(|target| target.distance(position) < MIRROR_RANGE);
+        if bot.difficulty.uses_powerups()
+            && unplaced_mirror
+            && target_close
+            && bot.mirror_hold.is_none()
+        {
+            bot.mirror_hold = Some(Timer::from_seconds(
+                rng.gen_range(0.3..1.2),
+                TimerMode::Once,
+            ));
+        }
+        if let Some(timer) = &mut bot.mirror_hold {
+            timer.tick(time.delta());
+            if timer.finished() {
+                bot.mirror_hold = None;
+            } else {
+                input.press(PlayerAction::Powerup);
+            }
+        }
+    }
+}
@@ -1,12 +1,21 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use crate::AppState;
+use crate::{
+    geometry::{LineSegment, Reflectable},
+    AppState,
+};
 
-use super::{Bullet, DespawnOnRestart, GameDirection, LifeTimer, Materials, Player};
+use super::{
+    Bullet, BulletKind, DespawnOnRestart, GameDirection, Invulnerable, LifeTimer, MatchSettings,
+    Materials, Platform, Player,
+};
 
 #[derive(Event)]
 pub struct BulletFiredEvent {
+    pub shooter: Entity,
+    pub player_id: i32,
+    pub kind: BulletKind,
     pub position: Vec2,
     pub direction: GameDirection,
 }
@@ -17,16 +26,32 @@ pub struct BulletHitEvent {
     pub bullet: Entity,
 }
 
+// A bullet hit that landed, i.e. the hit rules let it through and the target wasn't invulnerable
+#[derive(Event)]
+pub struct PlayerHitEvent {
+    pub target: Entity,
+}
+
 pub struct BulletPlugin;
 
+const BULLET_DAMAGE: f32 = 4.0;
+const BASE_KNOCKBACK: f32 = 10.0;
+// Every this much damage adds the base knockback once more
+const KNOCKBACK_DAMAGE_SCALE: f32 = 50.0;
+const SPREAD_COUNT: usize = 5;
+// Angle between the outermost bullets of a spread shot, in radians
+const SPREAD_ANGLE: f32 = 0.6;
+
 impl Plugin for BulletPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<BulletFiredEvent>()
             .add_event::<BulletHitEvent>()
+            .add_event::<PlayerHitEvent>()
             .add_systems(
                 Update,
                 (
                     spawn_bullet.run_if(in_state(AppState::InGame)),
+                    bounce_bullets.run_if(in_state(AppState::InGame)),
                     check_player_hit.run_if(in_state(AppState::InGame)),
                     player_hit.run_if(in_state(AppState::InGame)),
                 ),
@@ -34,68 +59,230 @@ impl Plugin for BulletPlugin {
     }
 }
 
+impl BulletKind {
+    fn speed(self) -> f32 {
+        match self {
+            BulletKind::Normal => 40.0,
+            BulletKind::Bouncing => 30.0,
+            BulletKind::Piercing => 60.0,
+            BulletKind::Spread => 35.0,
+            BulletKind::Heavy => 20.0,
+        }
+    }
+
+    fn size(self) -> Vec2 {
+        match self {
+            BulletKind::Normal | BulletKind::Spread => Vec2::new(0.4, 0.1),
+            BulletKind::Bouncing => Vec2::new(0.25, 0.25),
+            BulletKind::Piercing => Vec2::new(0.7, 0.08),
+            BulletKind::Heavy => Vec2::new(0.6, 0.3),
+        }
+    }
+
+    // Half extents of the collider, which is a lot smaller than the sprite
+    fn hitbox(self) -> Vec2 {
+        match self {
+            BulletKind::Normal | BulletKind::Spread | BulletKind::Piercing => Vec2::new(0.02, 0.03),
+            BulletKind::Bouncing => Vec2::new(0.05, 0.05),
+            BulletKind::Heavy => Vec2::new(0.1, 0.1),
+        }
+    }
+
+    // Seconds before the bullet disappears
+    fn lifetime(self) -> f32 {
+        match self {
+            BulletKind::Normal | BulletKind::Piercing => 1.0,
+            BulletKind::Bouncing => 2.5,
+            BulletKind::Spread => 0.5,
+            BulletKind::Heavy => 1.5,
+        }
+    }
+
+    // Multiplies the usual knockback
+    fn knockback(self) -> f32 {
+        match self {
+            BulletKind::Normal | BulletKind::Bouncing => 1.0,
+            BulletKind::Piercing => 0.7,
+            BulletKind::Spread => 0.5,
+            BulletKind::Heavy => 2.0,
+        }
+    }
+
+    fn bounces(self) -> u32 {
+        match self {
+            BulletKind::Bouncing => 3,
+            _ => 0,
+        }
+    }
+
+    // None keeps the default bullet material
+    pub fn color(self) -> Option<Color> {
+        match self {
+            BulletKind::Normal => None,
+            BulletKind::Bouncing => Some(Color::rgb(0.2, 0.9, 0.3)),
+            BulletKind::Piercing => Some(Color::rgb(0.3, 0.8, 1.0)),
+            BulletKind::Spread => Some(Color::rgb(1.0, 0.5, 0.1)),
+            BulletKind::Heavy => Some(Color::rgb(0.9, 0.1, 0.1)),
+        }
+    }
+
+    // Directions of the bullets fired by one shot, relative to where the player faces
+    fn fan_angles(self) -> Vec<f32> {
+        match self {
+            BulletKind::Spread => (0..SPREAD_COUNT)
+                .map(|i| SPREAD_ANGLE * (i as f32 / (SPREAD_COUNT - 1) as f32 - 0.5))
+                .collect(),
+            _ => vec![0.0],
+        }
+    }
+}
+
 pub fn spawn_bullet(
     mut commands: Commands,
     materials: Res<Materials>,
     mut ev_fired: EventReader<BulletFiredEvent>,
 ) {
     for BulletFiredEvent {
+        shooter,
+        player_id,
+        kind,
         position,
         direction,
     } in ev_fired.read()
     {
-        let velocity;
-        match direction {
-            GameDirection::Left => {
-                velocity = Vec2::new(-40.0, 0.0);
-            }
-            GameDirection::Right => {
-                velocity = Vec2::new(40.0, 0.0);
-            }
-        }
-        commands
-            .spawn((
+        let forward = match direction {
+            GameDirection::Left => Vec2::NEG_X,
+            GameDirection::Right => Vec2::X,
+        };
+        let hitbox = kind.hitbox();
+
+        for angle in kind.fan_angles() {
+            let velocity = Vec2::from_angle(angle).rotate(forward) * kind.speed();
+            let mut bullet = commands.spawn((
                 SpriteBundle {
                     sprite: Sprite {
-                        color: materials.bullet_material.into(),
-                        custom_size: Vec2::new(0.4, 0.1).into(),
+                        color: kind.color().unwrap_or(materials.bullet_material),
+                        custom_size: kind.size().into(),
                         ..Default::default()
                     },
-                    transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.)),
+                    transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.))
+                        .with_rotation(Quat::from_rotation_z(Vec2::X.angle_between(velocity))),
                     ..Default::default()
                 },
                 RigidBody::KinematicVelocityBased,
                 //LockedAxes::ROTATION_LOCKED,
                 //GravityScale(0.0),
-                Collider::cuboid(0.02, 0.03),
+                Collider::cuboid(hitbox.x, hitbox.y),
+                // Only has to notice the players it touches, player_hit applies the knockback so
+                // bullets the hit rules let through don't push anyone
+                Sensor,
                 ActiveEvents::COLLISION_EVENTS,
-                Bullet {},
+                Bullet {
+                    shooter: *shooter,
+                    player_id: *player_id,
+                    kind: *kind,
+                    bounces: kind.bounces(),
+                },
                 Velocity {
                     linvel: velocity,
                     angvel: 0.0,
                 },
                 DespawnOnRestart {},
-            ))
-            .insert(LifeTimer(Timer::from_seconds(1.0, TimerMode::Once)));
+            ));
+            bullet.insert(LifeTimer(Timer::from_seconds(
+                kind.lifetime(),
+                TimerMode::Once,
+            )));
+        }
     }
 }
 
+// Whether moving from `from` to `to` goes through the edge
+fn crosses(edge: LineSegment, from: Vec2, to: Vec2) -> bool {
+    let line = edge.get_line();
+    let (before, after) = (line.side(from), line.side(to));
+    if before * after >= 0.0 {
+        return false;
+    }
+    edge.on_strip(from + (to - from) * before / (before - after))
+}
+
+// Bullets don't collide with platforms, so look ahead for the edges they are about to cross
+fn bounce_bullets(
+    time: Res<Time>,
+    mut bullets: Query<(&mut Bullet, &mut Transform, &mut Velocity)>,
+    platforms: Query<(&Platform, &Transform), Without<Bullet>>,
+) {
+    // Flattening every platform is wasted work when nothing can bounce
+    if !bullets.iter().any(|(bullet, _, _)| bullet.bounces > 0) {
+        return;
+    }
+    let edges: Vec<LineSegment> = platforms
+        .iter()
+        .flat_map(|(platform, transform)| {
+            platform
+                .get_transformed_contour(transform)
+                .flatten(platform.flatten_tolerance)
+                .border()
+        })
+        .collect();
+
+    for (mut bullet, mut transform, mut velocity) in &mut bullets {
+        if bullet.bounces == 0 {
+            continue;
+        }
+        let position = transform.translation.xy();
+        let next = position + velocity.linvel * time.delta_seconds();
+        let Some(edge) = edges.iter().find(|edge| crosses(**edge, position, next)) else {
+            continue;
+        };
+
+        velocity.linvel = velocity
+            .linvel
+            .reflect_over_line(edge.get_line().centered_line());
+        transform.rotation = Quat::from_rotation_z(Vec2::X.angle_between(velocity.linvel));
+        bullet.bounces -= 1;
+    }
+}
+
+fn knockback(damage: f32) -> f32 {
+    BASE_KNOCKBACK * (1.0 + damage / KNOCKBACK_DAMAGE_SCALE)
+}
+
 fn player_hit(
     mut commands: Commands,
-    mut player_velocities: Query<(Entity, &mut Velocity), (With<Player>, Without<Bullet>)>,
-    mut bullet_velocities: Query<(Entity, &Velocity), (With<Bullet>, Without<Player>)>,
+    mut player_velocities: Query<
+        (Entity, &mut Velocity, &mut Player),
+        (Without<Bullet>, Without<Invulnerable>),
+    >,
+    bullets: Query<(&Bullet, &Velocity), Without<Player>>,
     mut ev_hit: EventReader<BulletHitEvent>,
+    mut send_player_hit: EventWriter<PlayerHitEvent>,
+    settings: Res<MatchSettings>,
 ) {
     for BulletHitEvent { target, bullet } in ev_hit.read() {
         // debug!("Bullet hit event!");
-        if let Ok((_, mut player_velocity)) = player_velocities.get_mut(*target) {
+        let Ok((bullet_info, bullet_velocity)) = bullets.get(*bullet) else {
+            continue;
+        };
+        if let Ok((_, mut player_velocity, mut player)) = player_velocities.get_mut(*target) {
             // debug!("Matched player!");
-            if let Ok((_, bullet_velocity)) = bullet_velocities.get(*bullet) {
-                //debug!("Matched bullet!");
-                player_velocity.linvel += bullet_velocity.linvel.normalize() * 10.0;
+            // Bullets the rules don't let through keep flying
+            if !settings.allows_hit(bullet_info, &player) {
+                continue;
             }
+            player.damage += BULLET_DAMAGE;
+            player.last_hit_by = Some(bullet_info.shooter);
+            player_velocity.linvel += bullet_velocity.linvel.normalize()
+                * knockback(player.damage)
+                * bullet_info.kind.knockback();
+            send_player_hit.send(PlayerHitEvent { target: *target });
         }
 
+        // Piercing bullets keep going after a hit
+        if bullet_info.kind == BulletKind::Piercing {
+            continue;
+        }
         if let Some(mut entity_commands) = commands.get_entity(*bullet) {
             entity_commands.despawn();
         }
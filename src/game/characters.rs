@@ -0,0 +1,250 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::{Ability, SpriteSheetDescription};
+
+// What a .character.ron file holds
+#[derive(Deserialize)]
+struct CharacterFile {
+    name: String,
+    sprite_sheet: String,
+    speed: f32,
+    jump_impulse: f32,
+    // Cooldowns in seconds
+    shoot_interval: f32,
+    butterfly_interval: f32,
+    dash_interval: f32,
+    // Half the collider size, in sprite pixels
+    hitbox: Vec2,
+    #[serde(default)]
+    ability: Option<Ability>,
+}
+
+#[derive(Asset, TypePath, Clone)]
+pub struct CharacterDescription {
+    pub name: String,
+    #[dependency]
+    pub sprite_sheet: Handle<SpriteSheetDescription>,
+    pub speed: f32,
+    pub jump_impulse: f32,
+    pub shoot_interval: f32,
+    pub butterfly_interval: f32,
+    pub dash_interval: f32,
+    pub hitbox: Vec2,
+    pub ability: Option<Ability>,
+}
+
+// Lists every character file, like the map catalog does for maps
+#[derive(Asset, TypePath)]
+pub struct CharacterRoster {
+    #[dependency]
+    pub characters: Vec<Handle<CharacterDescription>>,
+}
+
+#[derive(Resource)]
+pub struct CharacterRosterHandle(pub Handle<CharacterRoster>);
+
+#[derive(Default)]
+pub struct CharacterLoader;
+
+#[derive(Default)]
+pub struct CharacterRosterLoader;
+
+#[derive(Debug, Error)]
+pub enum CharacterLoaderError {
+    #[error("Could not read character file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse character file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("{path}: {field} has to be positive, it is {value}")]
+    BadStat {
+        path: String,
+        field: &'static str,
+        value: f32,
+    },
+    #[error("{path}: {field} can't be a negative number of seconds, it is {value}")]
+    BadInterval {
+        path: String,
+        field: &'static str,
+        value: f32,
+    },
+    #[error("{path}: hitbox has to be positive in both directions, it is {hitbox}")]
+    BadHitbox { path: String, hitbox: Vec2 },
+}
+
+impl AssetLoader for CharacterLoader {
+    type Asset = CharacterDescription;
+    type Settings = ();
+    type Error = CharacterLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let file = ron::de::from_bytes::<CharacterFile>(&bytes)?;
+
+            let path = || load_context.path().display().to_string();
+            for (field, value) in [("speed", file.speed), ("jump_impulse", file.jump_impulse)] {
+                if !value.is_finite() || value <= 0.0 {
+                    let path = path();
+                    return Err(CharacterLoaderError::BadStat { path, field, value });
+                }
+            }
+            // These become Durations, which can't be negative or NaN
+            for (field, value) in [
+                ("shoot_interval", file.shoot_interval),
+                ("butterfly_interval", file.butterfly_interval),
+                ("dash_interval", file.dash_interval),
+            ] {
+                if !value.is_finite() || value < 0.0 {
+                    let path = path();
+                    return Err(CharacterLoaderError::BadInterval { path, field, value });
+                }
+            }
+            if !file.hitbox.is_finite() || file.hitbox.min_element() <= 0.0 {
+                let path = path();
+                let hitbox = file.hitbox;
+                return Err(CharacterLoaderError::BadHitbox { path, hitbox });
+            }
+
+            Ok(CharacterDescription {
+                name: file.name,
+                sprite_sheet: load_context.load(file.sprite_sheet),
+                speed: file.speed,
+                jump_impulse: file.jump_impulse,
+                shoot_interval: file.shoot_interval,
+                butterfly_interval: file.butterfly_interval,
+                dash_interval: file.dash_interval,
+                hitbox: file.hitbox,
+                ability: file.ability,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["character.ron"]
+    }
+}
+
+impl AssetLoader for CharacterRosterLoader {
+    type Asset = CharacterRoster;
+    type Settings = ();
+    type Error = CharacterLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let paths = ron::de::from_bytes::<Vec<String>>(&bytes)?;
+
+            Ok(CharacterRoster {
+                characters: paths
+                    .into_iter()
+                    .map(|path| load_context.load(path))
+                    .collect(),
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["roster.ron"]
+    }
+}
+
+#[derive(SystemParam)]
+pub struct AvailableCharacters<'w> {
+    asset_server: Res<'w, AssetServer>,
+    roster: Res<'w, CharacterRosterHandle>,
+    rosters: Res<'w, Assets<CharacterRoster>>,
+    characters: Res<'w, Assets<CharacterDescription>>,
+    sprite_sheets: Res<'w, Assets<SpriteSheetDescription>>,
+}
+
+impl<'w> AvailableCharacters<'w> {
+    // None until the roster, every character in it and their sprite sheets have finished loading.
+    // Characters whose files failed to load are left out
+    pub fn all(&self) -> Option<Vec<&CharacterDescription>> {
+        let roster = self.rosters.get(&self.roster.0)?;
+        let loading = roster
+            .characters
+            .iter()
+            .any(|handle| self.loaded(handle).is_none() && !self.failed_to_load(handle));
+        if loading {
+            return None;
+        }
+        Some(
+            roster
+                .characters
+                .iter()
+                .filter_map(|handle| self.loaded(handle))
+                .collect(),
+        )
+    }
+
+    // Paths of the character files that failed to load, or whose sprite sheet did
+    pub fn failed(&self) -> Vec<String> {
+        self.rosters
+            .get(&self.roster.0)
+            .map(|roster| {
+                roster
+                    .characters
+                    .iter()
+                    .filter(|handle| self.failed_to_load(handle))
+                    .map(|handle| {
+                        handle
+                            .path()
+                            .map(|path| path.to_string())
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn loaded(&self, handle: &Handle<CharacterDescription>) -> Option<&CharacterDescription> {
+        self.characters
+            .get(handle)
+            .filter(|character| self.sprite_sheets.contains(&character.sprite_sheet))
+    }
+
+    fn failed_to_load(&self, handle: &Handle<CharacterDescription>) -> bool {
+        let failed = |id| self.asset_server.load_state(id) == LoadState::Failed;
+        failed(handle.id().untyped())
+            || self
+                .characters
+                .get(handle)
+                .is_some_and(|character| failed(character.sprite_sheet.id().untyped()))
+    }
+
+    // Picks wrap around, so any index names a character
+    pub fn get(&self, index: usize) -> Option<&CharacterDescription> {
+        let all = self.all()?;
+        if all.is_empty() {
+            return None;
+        }
+        Some(all[index % all.len()])
+    }
+
+    pub fn sprite_sheet(
+        &self,
+        character: &CharacterDescription,
+    ) -> Option<&SpriteSheetDescription> {
+        self.sprite_sheets.get(&character.sprite_sheet)
+    }
+}
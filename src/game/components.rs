@@ -4,26 +4,135 @@ use std::{
 };
 
 use bevy::{prelude::*, time::Stopwatch};
+use serde::{Deserialize, Serialize};
 
-use crate::geometry::{LineSegment, Polygon};
+use crate::geometry::{Contour, LineSegment, Polygon};
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Clone, PartialEq, Deserialize)]
 pub struct PlatformDescription {
     pub location: Vec2,
-    pub width: f32,
-    pub height: f32,
+    pub shape: PlatformShape,
+    // Players can jump up through it and drop down through it
+    #[serde(default)]
+    pub one_way: bool,
 }
 
-#[derive(Resource)]
+// Shapes are given relative to the platform location
+#[derive(Clone, PartialEq, Deserialize)]
+pub enum PlatformShape {
+    Rectangle {
+        width: f32,
+        height: f32,
+    },
+    // Flat bottom, with the top running from one height to the other
+    Slope {
+        width: f32,
+        left_height: f32,
+        right_height: f32,
+    },
+    Triangle {
+        vertices: [Vec2; 3],
+    },
+    // Angle in degrees, counter-clockwise
+    RotatedSlab {
+        width: f32,
+        height: f32,
+        angle: f32,
+    },
+    Polygon {
+        vertices: Vec<Vec2>,
+        #[serde(default)]
+        uvs: Option<Vec<Vec2>>,
+    },
+}
+
+impl PlatformShape {
+    // Vertices before rotation, which is the frame the texture is laid out in
+    pub fn local_vertices(&self) -> Vec<Vec2> {
+        match self {
+            PlatformShape::Rectangle { width, height }
+            | PlatformShape::RotatedSlab { width, height, .. } => {
+                let (half_width, half_height) = (width / 2.0, height / 2.0);
+                vec![
+                    Vec2::new(-half_width, -half_height),
+                    Vec2::new(half_width, -half_height),
+                    Vec2::new(half_width, half_height),
+                    Vec2::new(-half_width, half_height),
+                ]
+            }
+            PlatformShape::Slope {
+                width,
+                left_height,
+                right_height,
+            } => {
+                let half_width = width / 2.0;
+                let bottom = -left_height.max(*right_height) / 2.0;
+                vec![
+                    Vec2::new(-half_width, bottom),
+                    Vec2::new(half_width, bottom),
+                    Vec2::new(half_width, bottom + right_height),
+                    Vec2::new(-half_width, bottom + left_height),
+                ]
+            }
+            PlatformShape::Triangle { vertices } => vertices.to_vec(),
+            PlatformShape::Polygon { vertices, .. } => vertices.clone(),
+        }
+    }
+
+    pub fn rotation(&self) -> f32 {
+        match self {
+            PlatformShape::RotatedSlab { angle, .. } => angle.to_radians(),
+            _ => 0.0,
+        }
+    }
+
+    pub fn vertices(&self) -> Vec<Vec2> {
+        let rotation = Vec2::from_angle(self.rotation());
+        self.local_vertices()
+            .into_iter()
+            .map(|vertex| rotation.rotate(vertex))
+            .collect()
+    }
+}
+
+impl PlatformDescription {
+    pub fn bounds(&self) -> Rect {
+        let vertices = self.shape.vertices();
+        let min = vertices.iter().fold(Vec2::INFINITY, |min, v| min.min(*v));
+        let max = vertices
+            .iter()
+            .fold(Vec2::NEG_INFINITY, |max, v| max.max(*v));
+        Rect::from_corners(self.location + min, self.location + max)
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct SpawnPoint {
+    pub name: String,
+    pub position: Vec2,
+}
+
+#[derive(Resource, Asset, TypePath, Clone, Deserialize)]
 pub struct MapDescription {
+    pub name: String,
     pub platforms: Vec<PlatformDescription>,
     pub death_zone: f32,
+    pub spawn_points: Vec<SpawnPoint>,
+    pub powerup_regions: Vec<Rect>,
+}
+
+impl MapDescription {
+    // Players take the spawn points in order, sharing them if there are more players than points
+    pub fn spawn_point(&self, player_id: usize) -> &SpawnPoint {
+        &self.spawn_points[player_id % self.spawn_points.len()]
+    }
 }
 
 #[derive(Resource)]
 pub struct Materials {
     pub player_material: Color,
     pub floor_material: Handle<ColorMaterial>,
+    pub one_way_floor_material: Handle<ColorMaterial>,
     pub bullet_material: Color,
     pub death_zone_material: Color,
 }
@@ -38,20 +147,123 @@ pub enum GameDirection {
 pub enum PlayerAction {
     Left,
     Right,
+    Down,
     Jump,
+    Dash,
     Shoot,
     Powerup,
     Butterfly,
 }
 
-#[derive(Copy, Clone, PartialEq)]
+impl PlayerAction {
+    pub const ALL: [PlayerAction; 8] = [
+        PlayerAction::Left,
+        PlayerAction::Right,
+        PlayerAction::Down,
+        PlayerAction::Jump,
+        PlayerAction::Dash,
+        PlayerAction::Shoot,
+        PlayerAction::Powerup,
+        PlayerAction::Butterfly,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+// The actions a player holds this frame and the last, however they are controlled
+#[derive(Component, Clone, Copy, Default)]
+pub struct PlayerInput {
+    current: u8,
+    previous: u8,
+}
+
+impl PlayerInput {
+    // Called once per frame before the input sources press this frame's actions
+    pub fn advance(&mut self) {
+        self.previous = self.current;
+        self.current = 0;
+    }
+
+    pub fn press(&mut self, action: PlayerAction) {
+        self.current |= action.bit();
+    }
+
+    pub fn pressed(&self, action: PlayerAction) -> bool {
+        self.current & action.bit() != 0
+    }
+
+    pub fn just_pressed(&self, action: PlayerAction) -> bool {
+        self.pressed(action) && self.previous & action.bit() == 0
+    }
+
+    pub fn just_released(&self, action: PlayerAction) -> bool {
+        !self.pressed(action) && self.previous & action.bit() != 0
+    }
+}
+
+// Drives the player's input from the keyboard and gamepad
+#[derive(Component)]
+pub struct LocalControls(pub KeyBindings);
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct KeyBindings {
     pub left: KeyCode,
     pub right: KeyCode,
+    pub down: KeyCode,
     pub jump: KeyCode,
+    pub dash: KeyCode,
     pub shoot: KeyCode,
     pub powerup: KeyCode,
     pub butterfly: KeyCode,
+    pub gamepad: GamepadBindings,
+}
+
+// Movement always comes from the left stick or the d-pad, down included
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GamepadBindings {
+    pub gamepad: Gamepad,
+    pub jump: GamepadButtonType,
+    pub dash: GamepadButtonType,
+    pub shoot: GamepadButtonType,
+    pub powerup: GamepadButtonType,
+    pub butterfly: GamepadButtonType,
+}
+
+impl GamepadBindings {
+    pub fn new(gamepad: usize) -> Self {
+        Self {
+            gamepad: Gamepad::new(gamepad),
+            jump: GamepadButtonType::South,
+            dash: GamepadButtonType::LeftTrigger,
+            shoot: GamepadButtonType::West,
+            powerup: GamepadButtonType::RightTrigger,
+            butterfly: GamepadButtonType::East,
+        }
+    }
+
+    pub fn button(&self, action: PlayerAction) -> Option<GamepadButtonType> {
+        match action {
+            PlayerAction::Left | PlayerAction::Right | PlayerAction::Down => None,
+            PlayerAction::Jump => Some(self.jump),
+            PlayerAction::Dash => Some(self.dash),
+            PlayerAction::Shoot => Some(self.shoot),
+            PlayerAction::Powerup => Some(self.powerup),
+            PlayerAction::Butterfly => Some(self.butterfly),
+        }
+    }
+
+    pub fn set_button(&mut self, action: PlayerAction, button: GamepadButtonType) {
+        match action {
+            PlayerAction::Left | PlayerAction::Right | PlayerAction::Down => {}
+            PlayerAction::Jump => self.jump = button,
+            PlayerAction::Dash => self.dash = button,
+            PlayerAction::Shoot => self.shoot = button,
+            PlayerAction::Powerup => self.powerup = button,
+            PlayerAction::Butterfly => self.butterfly = button,
+        }
+    }
 }
 
 impl Index<PlayerAction> for KeyBindings {
@@ -61,7 +273,9 @@ impl Index<PlayerAction> for KeyBindings {
         match action {
             PlayerAction::Left => &self.left,
             PlayerAction::Right => &self.right,
+            PlayerAction::Down => &self.down,
             PlayerAction::Jump => &self.jump,
+            PlayerAction::Dash => &self.dash,
             PlayerAction::Shoot => &self.shoot,
             PlayerAction::Powerup => &self.powerup,
             PlayerAction::Butterfly => &self.butterfly,
@@ -74,7 +288,9 @@ impl IndexMut<PlayerAction> for KeyBindings {
         match action {
             PlayerAction::Left => &mut self.left,
             PlayerAction::Right => &mut self.right,
+            PlayerAction::Down => &mut self.down,
             PlayerAction::Jump => &mut self.jump,
+            PlayerAction::Dash => &mut self.dash,
             PlayerAction::Shoot => &mut self.shoot,
             PlayerAction::Powerup => &mut self.powerup,
             PlayerAction::Butterfly => &mut self.butterfly,
@@ -94,36 +310,114 @@ pub enum PowerupState {
     Mirror { r#type: MirrorType, placed: bool },
 }
 
+// Signature moves only some characters have
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+pub enum Ability {
+    // One more jump in the air
+    DoubleJump,
+    // Holding jump while falling slows the fall
+    Glide,
+}
+
 #[derive(Component, Clone)]
 pub struct Player {
     pub speed: f32,
     pub facing_direction: GameDirection,
     pub jump_impulse: f32,
     pub is_jumping: bool,
+    // Standing on top of a platform, as opposed to touching its side or underside
+    pub grounded: bool,
+    // Normal of the wall the player is touching, pointing away from it
+    pub wall_normal: Option<Vec2>,
+    pub last_grounded_time: Duration,
+    pub last_wall_jump_time: Duration,
+    pub last_jump_press_time: Option<Duration>,
     pub id: i32,
     pub last_shoot_time: Duration,
     pub shoot_interval: Duration,
     pub last_butterfly_time: Duration,
     pub butterfly_interval: Duration,
-    pub key_bindings: KeyBindings,
+    pub is_dashing: bool,
+    pub last_dash_time: Duration,
+    pub dash_interval: Duration,
+    pub ability: Option<Ability>,
+    // Spent by the double jump, refilled on landing
+    pub air_jump_available: bool,
     pub powerup: Option<PowerupState>,
     pub is_running: bool,
+    pub lives: u32,
+    // Percentage that scales the knockback from hits
+    pub damage: f32,
+    pub team: usize,
+    // Gets the kill if the player dies before anyone else hits them
+    pub last_hit_by: Option<Entity>,
+    pub kills: u32,
+    // Picked up from an ammo powerup, goes back to normal after bullet_kind_until
+    pub bullet_kind: BulletKind,
+    pub bullet_kind_until: Duration,
+}
+
+// Ignoring one-way platforms after pressing down and jump
+#[derive(Component)]
+pub struct DroppingThrough(pub Timer);
+
+// Bullets don't knock the player back until the timer runs out
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
+#[derive(Component)]
+pub struct PowerupTracker {
+    pub player: Entity,
+}
+
+#[derive(Component)]
+pub struct StockDisplay {
+    pub player: Entity,
+}
+
+#[derive(Component)]
+pub struct DamageDisplay {
+    pub player: Entity,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BulletKind {
+    Normal,
+    // Bounces off platform edges a few times
+    Bouncing,
+    // Flies through players instead of stopping at the first one
+    Piercing,
+    // A fan of bullets at once
+    Spread,
+    // Slow, but knocks players much further
+    Heavy,
 }
 
 #[derive(Component)]
-pub struct Bullet {}
+pub struct Bullet {
+    pub shooter: Entity,
+    pub player_id: i32,
+    pub kind: BulletKind,
+    // Platform edges it can still bounce off
+    pub bounces: u32,
+}
 
 #[derive(Component)]
 pub struct DespawnOnRestart {}
 
 #[derive(Component)]
 pub struct Platform {
-    polygon: Polygon,
+    contour: Contour,
+    pub flatten_tolerance: f32,
+    // Up direction of a one-way platform, it only blocks things coming from that side
+    pub one_way: Option<Vec2>,
 }
 
 #[derive(Component)]
 pub enum Powerup {
     Mirror(MirrorType),
+    // Changes the bullets the player fires for a while
+    Ammo(BulletKind),
 }
 
 #[derive(Component)]
@@ -142,22 +436,32 @@ pub struct LifeTimer(pub Timer);
 pub struct DeathZone {}
 
 impl Platform {
-    pub fn get_transformed_polygon(&self, transform: &Transform) -> Polygon {
-        Polygon::new(
-            self.polygon
-                .vertices()
-                .iter()
-                .map(|pt| transform.transform_point(pt.extend(0.0)).xy())
-                .collect(),
-            self.polygon.texture_coords().clone(),
-        )
+    pub fn get_transformed_contour(&self, transform: &Transform) -> Contour {
+        self.contour
+            .map_points(|pt| transform.transform_point(pt.extend(0.0)).xy())
+    }
+
+    pub fn contour(&self) -> &Contour {
+        &self.contour
     }
 
-    pub fn new(polygon: Polygon) -> Self {
-        Self { polygon }
+    pub fn flatten(&self) -> Polygon {
+        self.contour.flatten(self.flatten_tolerance)
+    }
+
+    pub fn new(contour: Contour, one_way: Option<Vec2>, flatten_tolerance: f32) -> Self {
+        Self {
+            contour,
+            flatten_tolerance,
+            one_way,
+        }
     }
 }
 
+// Maximum distance in world units between a curved platform edge and its flattened mesh
+#[derive(Resource)]
+pub struct FlattenTolerance(pub f32);
+
 #[derive(Component)]
 pub struct MirrorAnimation {
     pub timer: Timer,
@@ -181,7 +485,154 @@ impl Mirror {
     }
 }
 
-#[derive(Resource)]
+pub const MAX_PLAYERS: usize = 4;
+pub const MAX_STOCK: u32 = 9;
+
+// Characters can share a sprite sheet and players can pick the same character, so every slot
+// gets its own tint
+pub const PLAYER_TINTS: [Color; MAX_PLAYERS] = [
+    Color::WHITE,
+    Color::rgb(0.55, 0.75, 1.0),
+    Color::rgb(0.55, 1.0, 0.55),
+    Color::rgb(1.0, 0.85, 0.4),
+];
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum BotDifficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchSettings {
+    pub stock: u32,
+    pub player_count: usize,
+    // The last this many players are controlled by bots
+    pub bots: usize,
+    pub bot_difficulty: BotDifficulty,
+    // Index into the character roster for each player slot
+    pub characters: [usize; MAX_PLAYERS],
+    // Players alternate between two teams instead of everyone fighting everyone
+    pub teams: bool,
+    pub hit_rules: HitRules,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            stock: 3,
+            player_count: 2,
+            bots: 0,
+            bot_difficulty: BotDifficulty::Normal,
+            characters: [0, 1, 2, 3],
+            teams: false,
+            hit_rules: HitRules::default(),
+        }
+    }
+}
+
+impl MatchSettings {
+    // Keeps the settings in the ranges the menu offers, also for ones read from a settings file
+    pub fn clamp(&mut self) {
+        self.player_count = self.player_count.clamp(2, MAX_PLAYERS);
+        self.stock = self.stock.clamp(1, MAX_STOCK);
+        // Someone has to be playing
+        self.bots = self.bots.min(self.player_count - 1);
+    }
+
+    pub fn team(&self, player_id: i32) -> usize {
+        if self.teams {
+            player_id as usize % 2
+        } else {
+            player_id as usize
+        }
+    }
+
+    pub fn allows_hit(&self, bullet: &Bullet, target: &Player) -> bool {
+        if bullet.player_id == target.id {
+            self.hit_rules.self_hit
+        } else if self.team(bullet.player_id) == target.team {
+            self.hit_rules.team_damage
+        } else {
+            true
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HitRules {
+    // Bullets that come back to their shooter, e.g. off a mirror, still hit
+    pub self_hit: bool,
+    pub team_damage: bool,
+}
+
+impl Default for HitRules {
+    fn default() -> Self {
+        Self {
+            self_hit: true,
+            team_damage: false,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PlayerControls {
     pub controls: Vec<KeyBindings>,
 }
+
+impl Default for PlayerControls {
+    fn default() -> Self {
+        Self {
+            controls: vec![
+                KeyBindings {
+                    left: KeyCode::A,
+                    right: KeyCode::D,
+                    down: KeyCode::S,
+                    jump: KeyCode::W,
+                    dash: KeyCode::X,
+                    shoot: KeyCode::C,
+                    powerup: KeyCode::V,
+                    butterfly: KeyCode::B,
+                    gamepad: GamepadBindings::new(0),
+                },
+                KeyBindings {
+                    left: KeyCode::Left,
+                    right: KeyCode::Right,
+                    down: KeyCode::Down,
+                    jump: KeyCode::Up,
+                    dash: KeyCode::M,
+                    shoot: KeyCode::Comma,
+                    powerup: KeyCode::Period,
+                    butterfly: KeyCode::Slash,
+                    gamepad: GamepadBindings::new(1),
+                },
+                KeyBindings {
+                    left: KeyCode::J,
+                    right: KeyCode::L,
+                    down: KeyCode::K,
+                    jump: KeyCode::I,
+                    dash: KeyCode::H,
+                    shoot: KeyCode::O,
+                    powerup: KeyCode::P,
+                    butterfly: KeyCode::U,
+                    gamepad: GamepadBindings::new(2),
+                },
+                KeyBindings {
+                    left: KeyCode::Numpad4,
+                    right: KeyCode::Numpad6,
+                    down: KeyCode::Numpad5,
+                    jump: KeyCode::Numpad8,
+                    dash: KeyCode::NumpadAdd,
+                    shoot: KeyCode::Numpad0,
+                    powerup: KeyCode::NumpadDecimal,
+                    butterfly: KeyCode::NumpadEnter,
+                    gamepad: GamepadBindings::new(3),
+                },
+            ],
+        }
+    }
+}
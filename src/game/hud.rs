@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+
+use crate::AppState;
+
+use super::{
+    DamageDisplay, DespawnOnRestart, Player, PlayerSpawnEvent, PowerupTracker, StockDisplay,
+    PLAYER_TINTS,
+};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            spawn_player_huds.after(super::player::spawn_players),
+        )
+        .add_systems(
+            Update,
+            (update_stock_display, update_damage_display).run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn spawn_player_huds(
+    mut commands: Commands,
+    mut player_spawn_event: EventReader<PlayerSpawnEvent>,
+) {
+    // One panel per player, spread evenly along the bottom of the screen
+    let mut row = commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Percent(5.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::SpaceEvenly,
+                ..default()
+            },
+            ..default()
+        },
+        DespawnOnRestart {},
+    ));
+
+    for PlayerSpawnEvent { player_id, player } in player_spawn_event.read() {
+        info!("Creating HUD for player {:?}", player_id);
+
+        row.with_children(|parrent| {
+            parrent
+                .spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parrent| {
+                    parrent.spawn(TextBundle::from_section(
+                        format!("P{}", player_id + 1),
+                        TextStyle {
+                            font_size: 30.0,
+                            color: PLAYER_TINTS[*player_id as usize],
+                            ..default()
+                        },
+                    ));
+                    parrent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::VMin(5.0),
+                                height: Val::VMin(5.0),
+                                ..default()
+                            },
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        PowerupTracker { player: *player },
+                    ));
+                    parrent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 30.0,
+                                ..default()
+                            },
+                        ),
+                        StockDisplay { player: *player },
+                    ));
+                    parrent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 40.0,
+                                ..default()
+                            },
+                        ),
+                        DamageDisplay { player: *player },
+                    ));
+                });
+        });
+    }
+}
+
+fn update_stock_display(mut query: Query<(&mut Text, &StockDisplay)>, players: Query<&Player>) {
+    query.for_each_mut(|(mut text, stock_display)| {
+        // Players are despawned when they run out of lives
+        let stock = match players.get(stock_display.player) {
+            Ok(player) => format!("Lives: {}  KOs: {}", player.lives, player.kills),
+            Err(_) => "Out".to_string(),
+        };
+        if text.sections[0].value != stock {
+            text.sections[0].value = stock;
+        }
+    });
+}
+
+fn update_damage_display(mut query: Query<(&mut Text, &DamageDisplay)>, players: Query<&Player>) {
+    query.for_each_mut(|(mut text, damage_display)| {
+        if let Ok(player) = players.get(damage_display.player) {
+            let damage = format!("{:.0}%", player.damage);
+            if text.sections[0].value != damage {
+                text.sections[0].value = damage;
+                // Fades from white to red as the player gets easier to knock out
+                let heat = (player.damage / 150.0).min(1.0);
+                text.sections[0].style.color = Color::rgb(1.0, 1.0 - heat, 1.0 - heat);
+            }
+        }
+    });
+}
@@ -0,0 +1,166 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::AppState;
+
+use super::{KeyBindings, LocalControls, PlayerAction, PlayerInput};
+
+pub struct PlayerInputPlugin;
+
+// Systems that fill in PlayerInput run in this set, before the players act on it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerInputSet;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            advance_player_input.run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(
+            Update,
+            read_local_controls
+                .in_set(PlayerInputSet)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn advance_player_input(mut inputs: Query<&mut PlayerInput>) {
+    for mut input in &mut inputs {
+        input.advance();
+    }
+}
+
+fn read_local_controls(
+    controller: ControllerInput,
+    mut players: Query<(&LocalControls, &mut PlayerInput)>,
+) {
+    for (LocalControls(bindings), mut input) in &mut players {
+        for action in PlayerAction::ALL {
+            if controller.pressed(bindings, action) {
+                input.press(action);
+            }
+        }
+    }
+}
+
+// How far the stick has to be pushed before it counts as a direction
+const STICK_DEADZONE: f32 = 0.5;
+
+// Keyboard and gamepad state, read through a player's bindings
+#[derive(SystemParam)]
+pub struct ControllerInput<'w> {
+    keyboard: Res<'w, Input<KeyCode>>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl<'w> ControllerInput<'w> {
+    pub fn pressed(&self, bindings: &KeyBindings, action: PlayerAction) -> bool {
+        self.keyboard.pressed(bindings[action])
+            || self
+                .gamepad_button(bindings, action)
+                .is_some_and(|button| self.gamepad_buttons.pressed(button))
+            || self.stick_pressed(bindings, action)
+    }
+
+    fn gamepad_button(
+        &self,
+        bindings: &KeyBindings,
+        action: PlayerAction,
+    ) -> Option<GamepadButton> {
+        let gamepad = bindings.gamepad.gamepad;
+        let button_type = match action {
+            PlayerAction::Left => GamepadButtonType::DPadLeft,
+            PlayerAction::Right => GamepadButtonType::DPadRight,
+            PlayerAction::Down => GamepadButtonType::DPadDown,
+            action => bindings.gamepad.button(action)?,
+        };
+
+        Some(GamepadButton::new(gamepad, button_type))
+    }
+
+    fn stick_pressed(&self, bindings: &KeyBindings, action: PlayerAction) -> bool {
+        let stick = |axis_type| {
+            let axis = GamepadAxis::new(bindings.gamepad.gamepad, axis_type);
+            self.gamepad_axes.get(axis).unwrap_or(0.0)
+        };
+
+        match action {
+            PlayerAction::Left => stick(GamepadAxisType::LeftStickX) < -STICK_DEADZONE,
+            PlayerAction::Right => stick(GamepadAxisType::LeftStickX) > STICK_DEADZONE,
+            PlayerAction::Down => stick(GamepadAxisType::LeftStickY) < -STICK_DEADZONE,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::PlayerControls;
+
+    fn input_app() -> (App, Entity, KeyBindings) {
+        let bindings = PlayerControls::default().controls[0];
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .add_systems(Update, (advance_player_input, read_local_controls).chain());
+        let player = app
+            .world
+            .spawn((LocalControls(bindings), PlayerInput::default()))
+            .id();
+        (app, player, bindings)
+    }
+
+    fn buttons(app: &mut App) -> Mut<Input<GamepadButton>> {
+        app.world.resource_mut::<Input<GamepadButton>>()
+    }
+
+    fn player_input(app: &App, player: Entity) -> PlayerInput {
+        *app.world.get::<PlayerInput>(player).unwrap()
+    }
+
+    #[test]
+    fn bound_button_presses_its_action() {
+        let (mut app, player, bindings) = input_app();
+        let gamepad = bindings.gamepad.gamepad;
+        buttons(&mut app).press(GamepadButton::new(gamepad, bindings.gamepad.jump));
+        app.update();
+
+        let input = player_input(&app, player);
+        assert!(input.just_pressed(PlayerAction::Jump));
+        assert!(!input.pressed(PlayerAction::Shoot));
+
+        buttons(&mut app).release(GamepadButton::new(gamepad, bindings.gamepad.jump));
+        app.update();
+        assert!(player_input(&app, player).just_released(PlayerAction::Jump));
+    }
+
+    #[test]
+    fn other_gamepads_are_ignored() {
+        let (mut app, player, bindings) = input_app();
+        let other = Gamepad::new(bindings.gamepad.gamepad.id + 1);
+        buttons(&mut app).press(GamepadButton::new(other, bindings.gamepad.jump));
+        app.update();
+
+        assert!(!player_input(&app, player).pressed(PlayerAction::Jump));
+    }
+
+    #[test]
+    fn dpad_and_stick_move() {
+        let (mut app, player, bindings) = input_app();
+        let gamepad = bindings.gamepad.gamepad;
+        buttons(&mut app).press(GamepadButton::new(gamepad, GamepadButtonType::DPadDown));
+        app.world
+            .resource_mut::<Axis<GamepadAxis>>()
+            .set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX), -1.0);
+        app.update();
+
+        let input = player_input(&app, player);
+        assert!(input.pressed(PlayerAction::Down));
+        assert!(input.pressed(PlayerAction::Left));
+        assert!(!input.pressed(PlayerAction::Right));
+    }
+}
@@ -1,20 +1,57 @@
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
-use bevy_rapier2d::prelude::*;
+use std::time::Duration;
 
-use crate::{geometry::Polygon, AppState};
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    time::common_conditions::on_timer,
+    window::PrimaryWindow,
+};
+use bevy_rapier2d::{prelude::*, rapier::math::Vector};
+
+use crate::{
+    geometry::{Contour, Curve, Polygon},
+    AppState,
+};
 
 use super::{
-    DeathZone, DespawnOnRestart, MapDescription, Materials, Platform, PlatformDescription,
+    DeathZone, DespawnOnRestart, DroppingThrough, FlattenTolerance, MapDescription, Materials,
+    Platform, PlatformDescription, PlatformShape,
 };
 
 const GRASS_TILE_HEIGHT: f32 = 3.0;
 const GRASS_TILE_WIDTH: f32 = 1.5;
 
+// Curved edges may deviate this many screen pixels from their flattened mesh
+const FLATTEN_PIXEL_TOLERANCE: f32 = 0.5;
+
+// Slivers below this size are dropped instead of merged
+const MIN_FRAGMENT_EDGE: f32 = 0.001;
+const MIN_FRAGMENT_AREA: f32 = 0.01;
+const MERGE_MARGIN: f32 = 0.01;
+
+// How far from the up direction a contact normal may point and still be blocked
+const ONE_WAY_ALLOWED_ANGLE: f32 = 0.8;
+
 pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::InGame), spawn_floor);
+        app.insert_resource(FlattenTolerance(0.05))
+            .add_systems(OnEnter(AppState::InGame), spawn_floor)
+            .add_systems(
+                Update,
+                (update_flatten_tolerance, reflatten_platforms)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            // Runs after the reflection systems have spawned their fragments
+            .add_systems(
+                PostUpdate,
+                consolidate_platforms
+                    .run_if(on_timer(Duration::from_secs(2)))
+                    .run_if(in_state(AppState::InGame)),
+            );
     }
 }
 
@@ -23,17 +60,12 @@ pub fn spawn_floor(
     materials: Res<Materials>,
     mut meshes: ResMut<Assets<Mesh>>,
     map: Res<MapDescription>,
+    tolerance: Res<FlattenTolerance>,
 ) {
-    for PlatformDescription {
-        location,
-        width,
-        height,
-    } in map.platforms.iter().copied()
-    {
+    for platform in &map.platforms {
         spawn_platform(
-            location,
-            width,
-            height,
+            platform,
+            tolerance.0,
             &mut commands,
             &materials,
             &mut meshes,
@@ -42,56 +74,258 @@ pub fn spawn_floor(
     //add_death_zone(&mut commands, &materials, map.death_zone);
 }
 
-pub fn spawn_polygon(
+pub fn spawn_contour(
     location: Vec2,
-    polygon: Polygon,
+    contour: Contour,
+    one_way: Option<Vec2>,
+    tolerance: f32,
     commands: &mut Commands,
     materials: &Res<Materials>,
     meshes: &mut ResMut<Assets<Mesh>>,
 ) {
-    commands.spawn((
+    let polygon = contour.flatten(tolerance);
+    let material = match one_way {
+        Some(_) => materials.one_way_floor_material.clone_weak(),
+        None => materials.floor_material.clone_weak(),
+    };
+
+    let mut platform = commands.spawn((
         MaterialMesh2dBundle {
             mesh: meshes.add(Mesh::from(&polygon)).into(),
             transform: Transform::from_translation(Vec3::new(location.x, location.y, 0.)),
-            material: materials.floor_material.clone_weak(),
+            material,
             ..default()
         },
-        Collider::from(polygon.clone()),
+        Collider::from(polygon),
         RigidBody::Fixed,
         ActiveEvents::COLLISION_EVENTS,
         DespawnOnRestart {},
-        Platform::new(polygon),
+        Platform::new(contour, one_way, tolerance),
     ));
+    if one_way.is_some() {
+        platform.insert(ActiveHooks::MODIFY_SOLVER_CONTACTS);
+    }
+}
+
+// Lets one-way platforms only push back on things that come from above
+#[derive(SystemParam)]
+pub struct PlatformHooks<'w, 's> {
+    platforms: Query<'w, 's, &'static Platform>,
+    dropping: Query<'w, 's, (), With<DroppingThrough>>,
+}
+
+impl BevyPhysicsHooks for PlatformHooks<'_, '_> {
+    fn modify_solver_contacts(&self, mut context: ContactModificationContextView) {
+        let (collider1, collider2) = (context.collider1(), context.collider2());
+        // The allowed normal is given on the first collider's side
+        let one_way = |entity| self.platforms.get(entity).ok().and_then(|p| p.one_way);
+        let (up, other) = if let Some(up) = one_way(collider1) {
+            (up, collider2)
+        } else if let Some(up) = one_way(collider2) {
+            (-up, collider1)
+        } else {
+            return;
+        };
+
+        if self.dropping.contains(other) {
+            context.raw.solver_contacts.clear();
+        } else {
+            let up: Vector<f32> = up.into();
+            context
+                .raw
+                .update_as_oneway_platform(&up, ONE_WAY_ALLOWED_ANGLE);
+        }
+    }
+}
+
+fn update_flatten_tolerance(
+    cameras: Query<&OrthographicProjection, With<Camera>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut tolerance: ResMut<FlattenTolerance>,
+) {
+    let (Ok(projection), Ok(window)) = (cameras.get_single(), windows.get_single()) else {
+        return;
+    };
+
+    let world_per_pixel = projection.area.width() / window.width();
+    tolerance.0 = world_per_pixel * FLATTEN_PIXEL_TOLERANCE;
+}
+
+fn reflatten_platforms(
+    tolerance: Res<FlattenTolerance>,
+    mut platforms: Query<(&mut Platform, &mut Mesh2dHandle, &mut Collider)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (mut platform, mut mesh, mut collider) in platforms.iter_mut() {
+        let ratio = platform.flatten_tolerance / tolerance.0;
+        let has_arcs = platform
+            .contour()
+            .curves()
+            .iter()
+            .any(|curve| matches!(curve, Curve::Arc(_)));
+
+        // Only rebuild once the zoom has changed noticeably
+        if has_arcs && (ratio < 0.5 || ratio > 2.0) {
+            platform.flatten_tolerance = tolerance.0;
+            let polygon = platform.flatten();
+            *mesh = meshes.add(Mesh::from(&polygon)).into();
+            *collider = Collider::from(polygon);
+        }
+    }
+}
+
+fn consolidate_platforms(
+    mut commands: Commands,
+    platforms: Query<(Entity, &Transform, &Platform)>,
+    materials: Res<Materials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    tolerance: Res<FlattenTolerance>,
+) {
+    let mut groups: Vec<(Polygon, Option<Vec2>, Vec<Entity>)> = vec![];
+
+    for (entity, transform, platform) in platforms.iter() {
+        let polygon = platform
+            .get_transformed_contour(transform)
+            .sanitize(MIN_FRAGMENT_EDGE, MIN_FRAGMENT_AREA)
+            .and_then(|contour| {
+                contour
+                    .flatten(tolerance.0)
+                    .sanitize_polynomial(MIN_FRAGMENT_EDGE, MIN_FRAGMENT_AREA)
+            });
+
+        match polygon {
+            Some(polygon) => groups.push((polygon, platform.one_way, vec![entity])),
+            None => commands.entity(entity).despawn(),
+        }
+    }
+
+    let mut merged_any = true;
+    while merged_any {
+        merged_any = false;
+
+        'search: for a in 0..groups.len() {
+            for b in a + 1..groups.len() {
+                if !same_kind(groups[a].1, groups[b].1)
+                    || !bounding_boxes_touch(&groups[a].0, &groups[b].0)
+                {
+                    continue;
+                }
+
+                if let Some(merged) = merge_without_holes(&groups[a].0, &groups[b].0) {
+                    let (_, _, entities) = groups.swap_remove(b);
+                    groups[a].0 = merged;
+                    groups[a].2.extend(entities);
+                    merged_any = true;
+                    break 'search;
+                }
+            }
+        }
+    }
+
+    // Merged platforms lose their exact arcs, untouched ones keep them
+    for (polygon, one_way, entities) in groups
+        .into_iter()
+        .filter(|(_, _, entities)| entities.len() > 1)
+    {
+        for entity in entities {
+            commands.entity(entity).despawn();
+        }
+
+        spawn_contour(
+            Vec2::ZERO,
+            polygon.into(),
+            one_way,
+            tolerance.0,
+            &mut commands,
+            &materials,
+            &mut meshes,
+        );
+    }
+}
+
+// The union only keeps the outer ring, so any air the two enclose would turn solid.
+// Such pairs are left as separate platforms
+fn merge_without_holes(a: &Polygon, b: &Polygon) -> Option<Polygon> {
+    let [merged]: [Polygon; 1] = a.union(b).try_into().ok()?;
+    let overlap: f32 = a
+        .intersection(b)
+        .iter()
+        .map(|piece| piece.area().abs())
+        .sum();
+    let covered = a.area().abs() + b.area().abs() - overlap;
+    (merged.area().abs() <= covered + MIN_FRAGMENT_AREA).then_some(merged)
+}
+
+// Solid platforms only merge with solid ones, one-way ones with those facing the same way
+fn same_kind(a: Option<Vec2>, b: Option<Vec2>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => a.abs_diff_eq(b, MERGE_MARGIN),
+        _ => false,
+    }
+}
+
+fn bounding_boxes_touch(a: &Polygon, b: &Polygon) -> bool {
+    let (a_min, a_max) = a.bounding_box();
+    let (b_min, b_max) = b.bounding_box();
+
+    (a_min - MERGE_MARGIN).cmple(b_max).all() && (b_min - MERGE_MARGIN).cmple(a_max).all()
 }
 
 pub fn spawn_platform(
-    location: Vec2,
-    width: f32,
-    height: f32,
+    platform: &PlatformDescription,
+    tolerance: f32,
     commands: &mut Commands,
     materials: &Res<Materials>,
     meshes: &mut ResMut<Assets<Mesh>>,
 ) {
-    let half_width = width / 2.0;
-    let half_height = height / 2.0;
-    let tex_width = width / GRASS_TILE_WIDTH;
-    let tex_height = height / GRASS_TILE_HEIGHT;
-
-    let poly = Polygon::new(
-        vec![
-            Vec2::new(-half_width, -half_height),
-            Vec2::new(half_width, -half_height),
-            Vec2::new(half_width, half_height),
-            Vec2::new(-half_width, half_height),
-        ],
-        vec![
-            Vec2::new(0.0, tex_height),
-            Vec2::new(tex_width, tex_height),
-            Vec2::new(tex_width, 0.0),
-            Vec2::new(0.0, 0.0),
-        ],
+    let texture_coords = match &platform.shape {
+        PlatformShape::Polygon { uvs: Some(uvs), .. } => uvs.clone(),
+        shape => grass_texture_coords(&shape.local_vertices()),
+    };
+    let poly = Polygon::new(platform.shape.vertices(), texture_coords);
+
+    spawn_contour(
+        platform.location,
+        poly.into(),
+        platform.one_way.then_some(Vec2::Y),
+        tolerance,
+        commands,
+        materials,
+        meshes,
     );
-    spawn_polygon(location, poly, commands, materials, meshes);
+}
+
+// Tiles the grass horizontally, with its top edge following the top of the shape
+fn grass_texture_coords(vertices: &[Vec2]) -> Vec<Vec2> {
+    let left = vertices.iter().map(|v| v.x).fold(f32::INFINITY, f32::min);
+
+    vertices
+        .iter()
+        .map(|v| {
+            Vec2::new(
+                (v.x - left) / GRASS_TILE_WIDTH,
+                (surface_height(vertices, v.x) - v.y) / GRASS_TILE_HEIGHT,
+            )
+        })
+        .collect()
+}
+
+// Highest point of the outline straight above or below x
+fn surface_height(vertices: &[Vec2], x: f32) -> f32 {
+    let n = vertices.len();
+
+    (0..n)
+        .map(|i| (vertices[i], vertices[(i + 1) % n]))
+        .filter(|(a, b)| a.x.min(b.x) <= x && x <= a.x.max(b.x))
+        .map(|(a, b)| {
+            if a.x == b.x {
+                a.y.max(b.y)
+            } else {
+                a.y + (b.y - a.y) * (x - a.x) / (b.x - a.x)
+            }
+        })
+        .fold(f32::NEG_INFINITY, f32::max)
 }
 
 fn add_death_zone(commands: &mut Commands, materials: &Res<Materials>, y: f32) {
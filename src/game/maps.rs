@@ -1,62 +1,186 @@
-use bevy::math::Vec2;
-
-use super::{MapDescription, PlatformDescription};
-
-pub fn get_map1() -> MapDescription {
-    MapDescription {
-        platforms: vec![
-            PlatformDescription {
-                location: Vec2::new(0.0, 0.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(0.0, 10.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(15.0, 5.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(15.0, -5.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(-15.0, 5.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(-15.0, -5.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(-25.0, -5.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(25.0, -5.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(-25.0, 10.0),
-                width: 10.0,
-                height: 1.0,
-            },
-            PlatformDescription {
-                location: Vec2::new(25.0, 10.0),
-                width: 10.0,
-                height: 1.0,
-            },
-        ],
-
-        death_zone: -40.0,
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::BoxedFuture,
+};
+use thiserror::Error;
+
+use super::{MapDescription, PlatformShape};
+
+// Lists every map file, so they can be found without reading directories (which the web can't)
+#[derive(Asset, TypePath)]
+pub struct MapCatalog {
+    #[dependency]
+    pub maps: Vec<Handle<MapDescription>>,
+}
+
+#[derive(Resource)]
+pub struct MapCatalogHandle(pub Handle<MapCatalog>);
+
+#[derive(Resource, Default)]
+pub struct SelectedMap(pub usize);
+
+#[derive(Default)]
+pub struct MapLoader;
+
+#[derive(Default)]
+pub struct MapCatalogLoader;
+
+#[derive(Debug, Error)]
+pub enum MapLoaderError {
+    #[error("Could not read map file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse map file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Map has no spawn points")]
+    NoSpawnPoints,
+    #[error("Polygon of platform {platform} has {count} vertices, it needs at least 3")]
+    TooFewVertices { platform: usize, count: usize },
+    #[error("Polygon of platform {platform} has {vertices} vertices but {uvs} uvs")]
+    UvCountMismatch {
+        platform: usize,
+        vertices: usize,
+        uvs: usize,
+    },
+    #[error("Powerup region {region} from {min} to {max} has no area")]
+    EmptyPowerupRegion { region: usize, min: Vec2, max: Vec2 },
+}
+
+impl AssetLoader for MapLoader {
+    type Asset = MapDescription;
+    type Settings = ();
+    type Error = MapLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let map = ron::de::from_bytes::<MapDescription>(&bytes)?;
+            if map.spawn_points.is_empty() {
+                return Err(MapLoaderError::NoSpawnPoints);
+            }
+            for (platform, description) in map.platforms.iter().enumerate() {
+                if let PlatformShape::Polygon { vertices, uvs } = &description.shape {
+                    if vertices.len() < 3 {
+                        return Err(MapLoaderError::TooFewVertices {
+                            platform,
+                            count: vertices.len(),
+                        });
+                    }
+                    if let Some(uvs) = uvs.as_ref().filter(|uvs| uvs.len() != vertices.len()) {
+                        return Err(MapLoaderError::UvCountMismatch {
+                            platform,
+                            vertices: vertices.len(),
+                            uvs: uvs.len(),
+                        });
+                    }
+                }
+            }
+            // Powerups are placed by area, so every region needs some
+            for (region, Rect { min, max }) in map.powerup_regions.iter().copied().enumerate() {
+                if !min.is_finite() || !max.is_finite() || min.cmpge(max).any() {
+                    return Err(MapLoaderError::EmptyPowerupRegion { region, min, max });
+                }
+            }
+            Ok(map)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.ron"]
+    }
+}
+
+impl AssetLoader for MapCatalogLoader {
+    type Asset = MapCatalog;
+    type Settings = ();
+    type Error = MapLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let paths = ron::de::from_bytes::<Vec<String>>(&bytes)?;
+
+            Ok(MapCatalog {
+                maps: paths
+                    .into_iter()
+                    .map(|path| load_context.load(path))
+                    .collect(),
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["catalog.ron"]
+    }
+}
+
+#[derive(SystemParam)]
+pub struct AvailableMaps<'w> {
+    asset_server: Res<'w, AssetServer>,
+    catalog: Res<'w, MapCatalogHandle>,
+    catalogs: Res<'w, Assets<MapCatalog>>,
+    maps: Res<'w, Assets<MapDescription>>,
+}
+
+impl<'w> AvailableMaps<'w> {
+    // None until the catalog and every map in it have finished loading. Maps that failed to
+    // load are left out, so one broken file doesn't keep the others from being played
+    pub fn all(&self) -> Option<Vec<&MapDescription>> {
+        let catalog = self.catalogs.get(&self.catalog.0)?;
+        let loading = catalog
+            .maps
+            .iter()
+            .any(|handle| !self.maps.contains(handle) && !self.failed_to_load(handle));
+        if loading {
+            return None;
+        }
+        Some(
+            catalog
+                .maps
+                .iter()
+                .filter_map(|handle| self.maps.get(handle))
+                .collect(),
+        )
+    }
+
+    // Paths of the maps in the catalog that failed to load
+    pub fn failed(&self) -> Vec<String> {
+        self.catalogs
+            .get(&self.catalog.0)
+            .map(|catalog| {
+                catalog
+                    .maps
+                    .iter()
+                    .filter(|handle| self.failed_to_load(handle))
+                    .map(|handle| {
+                        handle
+                            .path()
+                            .map(|path| path.to_string())
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn failed_to_load(&self, handle: &Handle<MapDescription>) -> bool {
+        self.asset_server.load_state(handle.id()) == LoadState::Failed
+    }
+
+    pub fn get(&self, index: usize) -> Option<&MapDescription> {
+        self.all()?.get(index).copied()
     }
 }
@@ -21,6 +21,21 @@ use powerups::*;
 mod maps;
 use maps::*;
 
+mod hud;
+use hud::*;
+
+mod input;
+use input::*;
+
+mod bot;
+use bot::*;
+
+mod animation;
+use animation::*;
+
+mod characters;
+use characters::*;
+
 use bevy::{
     prelude::*,
     render::texture::{
@@ -33,19 +48,38 @@ use crate::AppState;
 
 use self::{butterfly::ButterflyPlugin, reflections::ReflectionsPlugin};
 
-pub use components::{KeyBindings, PlayerAction, PlayerControls};
+pub use characters::AvailableCharacters;
+pub use components::{
+    BotDifficulty, KeyBindings, MapDescription, MatchSettings, PlayerAction, PlayerControls,
+    MAX_PLAYERS,
+};
+pub use maps::{AvailableMaps, SelectedMap};
 
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+        app.add_plugins(RapierPhysicsPlugin::<PlatformHooks>::default())
             .add_plugins(MapPlugin)
             .add_plugins(BulletPlugin)
             .add_plugins(PlayerPlugin)
             .add_plugins(ButterflyPlugin)
             .add_plugins(ReflectionsPlugin)
             .add_plugins(PowerupsPlugin)
+            .add_plugins(HudPlugin)
+            .add_plugins(PlayerInputPlugin)
+            .add_plugins(BotPlugin)
+            .add_plugins(AnimationPlugin)
+            .init_asset::<MapDescription>()
+            .init_asset::<MapCatalog>()
+            .init_asset_loader::<MapLoader>()
+            .init_asset_loader::<MapCatalogLoader>()
+            .init_asset::<CharacterDescription>()
+            .init_asset::<CharacterRoster>()
+            .init_asset_loader::<CharacterLoader>()
+            .init_asset_loader::<CharacterRosterLoader>()
+            .init_resource::<SelectedMap>()
+            .init_resource::<MatchSettings>()
             .add_systems(PreStartup, setup)
             .add_systems(Update, game_over)
             .add_systems(OnEnter(AppState::MainMenu), cleanup)
@@ -70,8 +104,9 @@ fn game_over(
     mut commands: Commands,
     mut read_game_over_event: EventReader<GameOverEvent>,
     mut app_state: ResMut<NextState<AppState>>,
+    settings: Res<MatchSettings>,
 ) {
-    for GameOverEvent { lost_player } in read_game_over_event.read() {
+    for GameOverEvent { winner } in read_game_over_event.read() {
         app_state.set(AppState::GameOver);
         commands
             .spawn((
@@ -89,7 +124,13 @@ fn game_over(
             ))
             .with_children(|parrent| {
                 parrent.spawn(TextBundle::from_section(
-                    format!("Player {} won", 2 - lost_player),
+                    match winner {
+                        Some(winner) if settings.teams => {
+                            format!("Team {} won", settings.team(*winner) + 1)
+                        }
+                        Some(winner) => format!("Player {} won", winner + 1),
+                        None => "Draw".to_string(),
+                    },
                     TextStyle {
                         font_size: 100.0,
                         ..default()
@@ -128,11 +169,21 @@ fn setup(
     });
     commands.insert_resource(Materials {
         player_material: Color::rgb(0.969, 0.769, 0.784).into(),
-        floor_material: materials.add(floor_texture.into()),
+        floor_material: materials.add(floor_texture.clone().into()),
+        // Same grass, see-through so one-way platforms stand out
+        one_way_floor_material: materials.add(ColorMaterial {
+            color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+            texture: Some(floor_texture),
+        }),
         death_zone_material: Color::rgb(0.5, 0.0, 0.).into(),
         bullet_material: Color::rgb(0.8, 0.8, 0.).into(),
     });
-    commands.insert_resource(get_map1());
+    commands.insert_resource(MapCatalogHandle(
+        asset_server.load("maps/index.catalog.ron"),
+    ));
+    commands.insert_resource(CharacterRosterHandle(
+        asset_server.load("characters/index.roster.ron"),
+    ));
     commands.spawn(new_camera_2d());
 }
 
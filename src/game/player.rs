@@ -8,36 +8,75 @@ use bevy_rapier2d::{na::ComplexField, prelude::*};
 
 use crate::AppState;
 
-use super::MapDescription;
 use super::{
-    butterfly::ButterflyEvent, reflections::ReflectionEvent, AnimationIndices, AnimationTimer,
-    BulletFiredEvent, DeathZone, DespawnOnRestart, GameDirection, KeyBindings, Mirror, MirrorType,
-    Platform, Player, PlayerControls, PowerupState,
+    butterfly::ButterflyEvent, reflections::ReflectionEvent, BulletFiredEvent, BulletKind,
+    DeathZone, DespawnOnRestart, DroppingThrough, GameDirection, Invulnerable, LocalControls,
+    MatchSettings, Mirror, MirrorType, Platform, Player, PlayerAction, PlayerAnimation,
+    PlayerControls, PlayerInput, PowerupState, PLAYER_TINTS,
+};
+use super::{
+    Ability, AvailableCharacters, BotController, CharacterDescription, MapDescription,
+    PlayerInputSet,
 };
 
 pub struct PlayerPlugin;
 
+const RESPAWN_INVULNERABILITY_SECS: f32 = 2.0;
+// Players can still jump this long after running off a ledge
+const COYOTE_TIME: Duration = Duration::from_millis(100);
+// A jump pressed this long before landing still goes off when the player lands
+const JUMP_BUFFER_TIME: Duration = Duration::from_millis(150);
+// Letting go of jump while still rising keeps this much of the upward speed
+const JUMP_RELEASE_DAMPING: f32 = 0.5;
+// Sideways speed and length of a dash, and how long it keeps bullets from knocking the player back
+const DASH_SPEED: f32 = 30.0;
+const DASH_DURATION: Duration = Duration::from_millis(150);
+const DASH_INVULNERABILITY_SECS: f32 = 0.2;
+// Minimum time spent falling through a one-way platform, so it lets go of the player
+const DROP_THROUGH_SECS: f32 = 0.3;
+// How far a contact normal may tilt from straight up and still count as ground,
+// anything steeper that isn't a ceiling is a wall
+const GROUND_NORMAL_MIN_Y: f32 = 0.7;
+// Fastest fall while pushing into a wall
+const WALL_SLIDE_SPEED: f32 = 5.0;
+// Fastest fall while gliding
+const GLIDE_SPEED: f32 = 4.0;
+// Sideways speed of a wall jump, away from the wall
+const WALL_JUMP_SPEED: f32 = 15.0;
+// Steering is ignored for a moment after a wall jump so pushing into the wall doesn't cancel it
+const WALL_JUMP_STEERING_LOCK: Duration = Duration::from_millis(150);
+
 #[derive(Event)]
 pub struct GameOverEvent {
-    pub lost_player: i32,
+    // None if the last players went out at the same time
+    pub winner: Option<i32>,
 }
 
+// Set once the game over event has been sent, so it only goes out once per match
+#[derive(Resource, Default)]
+struct MatchDecided(bool);
+
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<GameOverEvent>()
             .add_event::<MirrorSpawnEvent>()
             .add_event::<PlayerSpawnEvent>()
             .add_event::<MirrorUseEvent>()
-            .add_systems(OnEnter(AppState::InGame), spawn_players)
+            .init_resource::<MatchDecided>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (spawn_players, reset_match_decided),
+            )
             .add_systems(
                 Update,
                 (
                     camera_follow_players.run_if(in_state(AppState::InGame)),
                     (
-                        player_controller,
-                        jump_reset,
-                        check_death_collision,
-                        animate_sprite,
+                        player_controller.after(PlayerInputSet),
+                        update_grounded.before(player_controller),
+                        (check_death_collision, check_last_player_standing).chain(),
+                        tick_invulnerability,
+                        tick_dropping_through,
                         draw_mirrors,
                         spawn_mirror,
                         use_mirror,
@@ -48,59 +87,49 @@ impl Plugin for PlayerPlugin {
     }
 }
 
-fn animate_sprite(
-    time: Res<Time>,
-    mut query: Query<(
-        &AnimationIndices,
-        &mut AnimationTimer,
-        &mut TextureAtlasSprite,
-        &Player,
-    )>,
-) {
-    for (indices, mut timer, mut sprite, player) in &mut query {
-        if player.is_running {
-            timer.tick(time.delta());
-            if timer.just_finished() {
-                sprite.index = if sprite.index == indices.last {
-                    indices.first
-                } else {
-                    sprite.index + 1
-                };
-            }
-        } else {
-            sprite.index = 0;
-        }
-    }
-}
-
 pub fn spawn_players(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    characters: AvailableCharacters,
     mut spawn_event_sender: EventWriter<PlayerSpawnEvent>,
     controls: Res<PlayerControls>,
+    map: Res<MapDescription>,
+    settings: Res<MatchSettings>,
 ) {
     info!("Spawning Players");
-    spawn_player(
-        0,
-        Transform::from_xyz(-1.0, 7.0, 0.0),
-        "textures/player0.png".to_string(),
-        &mut commands,
-        &asset_server,
-        &mut texture_atlases,
-        controls.controls[0],
-        &mut spawn_event_sender,
-    );
-    spawn_player(
-        1,
-        Transform::from_xyz(1.0, 7.0, 0.0),
-        "textures/player1.png".to_string(),
-        &mut commands,
-        &asset_server,
-        &mut texture_atlases,
-        controls.controls[1],
-        &mut spawn_event_sender,
-    );
+    let humans = settings.player_count - settings.bots;
+    for player_id in 0..settings.player_count {
+        let spawn_point = map.spawn_point(player_id);
+        info!("Player {} spawns at {}", player_id, spawn_point.name);
+        // The menu doesn't start the match before the characters have loaded
+        let character = characters
+            .get(settings.characters[player_id])
+            .expect("Characters should be loaded");
+        let atlas = characters
+            .sprite_sheet(character)
+            .expect("Character sprite sheets should be loaded")
+            .atlas
+            .clone();
+        let player = spawn_player(
+            player_id as i32,
+            Transform::from_translation(spawn_point.position.extend(0.0)),
+            character,
+            atlas,
+            PLAYER_TINTS[player_id],
+            &mut commands,
+            settings.stock,
+            settings.team(player_id as i32),
+            &mut spawn_event_sender,
+        );
+        if player_id < humans {
+            commands
+                .entity(player)
+                .insert(LocalControls(controls.controls[player_id]));
+        } else {
+            commands
+                .entity(player)
+                .insert(BotController::new(settings.bot_difficulty));
+        }
+    }
 }
 
 #[derive(Event)]
@@ -112,19 +141,14 @@ pub struct PlayerSpawnEvent {
 fn spawn_player(
     player_id: i32,
     position: Transform,
-    texture: String,
+    character: &CharacterDescription,
+    texture_atlas: Handle<TextureAtlas>,
+    tint: Color,
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-    key_bindings: KeyBindings,
+    lives: u32,
+    team: usize,
     spawn_event_sender: &mut EventWriter<PlayerSpawnEvent>,
-) {
-    let texture_handle = asset_server.load(texture);
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2::new(24.0, 24.0), 7, 1, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    // Use only the subset of sprites in the sheet that make up the run animation
-    let animation_indices = AnimationIndices { first: 1, last: 6 };
+) -> Entity {
     let scale = Vec3 {
         x: 0.125,
         y: 0.125,
@@ -133,32 +157,51 @@ fn spawn_player(
 
     let entity = commands.spawn((
         SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle,
-            sprite: TextureAtlasSprite::new(animation_indices.first),
+            texture_atlas,
+            sprite: TextureAtlasSprite {
+                color: tint,
+                ..TextureAtlasSprite::new(0)
+            },
             transform: position.with_scale(scale),
             ..Default::default()
         },
-        animation_indices,
-        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+        PlayerAnimation::new(character.sprite_sheet.clone()),
         RigidBody::Dynamic,
         LockedAxes::ROTATION_LOCKED,
-        Collider::cuboid(8., 12.),
+        Collider::cuboid(character.hitbox.x, character.hitbox.y),
         KinematicCharacterController::default(),
         ActiveEvents::COLLISION_EVENTS,
         Player {
-            speed: 10.0,
+            speed: character.speed,
             facing_direction: GameDirection::Right,
-            jump_impulse: 30.0,
+            jump_impulse: character.jump_impulse,
             is_jumping: false,
+            grounded: false,
+            wall_normal: None,
+            last_grounded_time: Duration::new(0, 0),
+            last_wall_jump_time: Duration::new(0, 0),
+            last_jump_press_time: None,
             id: player_id,
             last_shoot_time: Duration::new(0, 0),
-            shoot_interval: Duration::new(0, 100_000_000),
+            shoot_interval: Duration::from_secs_f32(character.shoot_interval),
             last_butterfly_time: Duration::new(0, 0),
-            butterfly_interval: Duration::new(3, 0),
-            key_bindings,
+            butterfly_interval: Duration::from_secs_f32(character.butterfly_interval),
+            is_dashing: false,
+            last_dash_time: Duration::new(0, 0),
+            dash_interval: Duration::from_secs_f32(character.dash_interval),
+            ability: character.ability,
+            air_jump_available: false,
             powerup: None,
             is_running: false,
+            lives,
+            damage: 0.0,
+            team,
+            last_hit_by: None,
+            kills: 0,
+            bullet_kind: BulletKind::Normal,
+            bullet_kind_until: Duration::new(0, 0),
         },
+        PlayerInput::default(),
         Velocity {
             linvel: Vec2::new(0.0, 0.0),
             angvel: 0.0,
@@ -168,7 +211,8 @@ fn spawn_player(
     spawn_event_sender.send(PlayerSpawnEvent {
         player_id,
         player: entity.id(),
-    })
+    });
+    entity.id()
 }
 
 fn camera_follow_players(
@@ -229,13 +273,155 @@ pub fn player_go_right(
     player.is_running = true;
 }
 
-pub fn player_jump(player: &mut Player, velocity: &mut Velocity) {
-    if !player.is_jumping {
+fn jump_buffered(player: &Player, time: &Res<Time>) -> bool {
+    player
+        .last_jump_press_time
+        .is_some_and(|press_time| press_time + JUMP_BUFFER_TIME > time.elapsed())
+}
+
+fn on_ground(player: &Player, time: &Res<Time>) -> bool {
+    player.grounded || player.last_grounded_time + COYOTE_TIME > time.elapsed()
+}
+
+pub fn player_jump(player: &mut Player, velocity: &mut Velocity, time: &Res<Time>) {
+    if jump_buffered(player, time) && on_ground(player, time) && !player.is_jumping {
+        end_dash(player, velocity);
         player.is_jumping = true;
+        player.last_jump_press_time = None;
         velocity.linvel = Vec2::new(velocity.linvel.x, player.jump_impulse).into();
     }
 }
 
+pub fn player_wall_jump(
+    player: &mut Player,
+    velocity: &mut Velocity,
+    sprite: &mut TextureAtlasSprite,
+    time: &Res<Time>,
+) {
+    let Some(wall_normal) = player.wall_normal else {
+        return;
+    };
+    if !jump_buffered(player, time) || on_ground(player, time) {
+        return;
+    }
+
+    end_dash(player, velocity);
+    player.is_jumping = true;
+    player.last_jump_press_time = None;
+    player.last_wall_jump_time = time.elapsed();
+    velocity.linvel = Vec2::new(
+        wall_normal.x.signum() * WALL_JUMP_SPEED,
+        player.jump_impulse,
+    );
+
+    let away_left = wall_normal.x < 0.0;
+    player.facing_direction = if away_left {
+        GameDirection::Left
+    } else {
+        GameDirection::Right
+    };
+    sprite.flip_x = away_left;
+}
+
+pub fn player_air_jump(player: &mut Player, velocity: &mut Velocity, time: &Res<Time>) {
+    if player.ability != Some(Ability::DoubleJump) || !player.air_jump_available {
+        return;
+    }
+    if !jump_buffered(player, time) || on_ground(player, time) {
+        return;
+    }
+
+    end_dash(player, velocity);
+    player.is_jumping = true;
+    player.air_jump_available = false;
+    player.last_jump_press_time = None;
+    velocity.linvel.y = player.jump_impulse;
+}
+
+pub fn player_glide(player: &Player, velocity: &mut Velocity, input: &PlayerInput) {
+    let gliding = player.ability == Some(Ability::Glide)
+        && !player.grounded
+        && input.pressed(PlayerAction::Jump);
+    if gliding && velocity.linvel.y < -GLIDE_SPEED {
+        velocity.linvel.y = -GLIDE_SPEED;
+    }
+}
+
+// Falls slowly while airborne and pushing into a wall
+pub fn player_wall_slide(player: &Player, velocity: &mut Velocity, input: &PlayerInput) {
+    let Some(wall_normal) = player.wall_normal else {
+        return;
+    };
+    let pushing_into_wall = if wall_normal.x > 0.0 {
+        input.pressed(PlayerAction::Left)
+    } else {
+        input.pressed(PlayerAction::Right)
+    };
+
+    if !player.grounded && pushing_into_wall && velocity.linvel.y < -WALL_SLIDE_SPEED {
+        velocity.linvel.y = -WALL_SLIDE_SPEED;
+    }
+}
+
+// Short hops: letting go early cuts the jump off
+pub fn player_release_jump(player: &mut Player, velocity: &mut Velocity) {
+    if player.is_jumping && velocity.linvel.y > 0.0 {
+        velocity.linvel.y *= JUMP_RELEASE_DAMPING;
+    }
+}
+
+pub fn player_dash(
+    player: &mut Player,
+    velocity: &mut Velocity,
+    entity: Entity,
+    invulnerable: bool,
+    commands: &mut Commands,
+    time: &Res<Time>,
+) {
+    if player.last_dash_time + player.dash_interval > time.elapsed() {
+        return;
+    }
+
+    let direction = match player.facing_direction {
+        GameDirection::Left => -1.0,
+        GameDirection::Right => 1.0,
+    };
+    velocity.linvel = Vec2::new(direction * DASH_SPEED, 0.0);
+    player.is_dashing = true;
+    player.last_dash_time = time.elapsed();
+
+    // Don't cut a longer invulnerability short
+    if !invulnerable {
+        commands
+            .entity(entity)
+            .insert(Invulnerable(Timer::from_seconds(
+                DASH_INVULNERABILITY_SECS,
+                TimerMode::Once,
+            )));
+    }
+}
+
+// Dashes go in a straight line, then drop back to running speed
+fn player_update_dash(player: &mut Player, velocity: &mut Velocity, time: &Res<Time>) {
+    if !player.is_dashing {
+        return;
+    }
+
+    if player.last_dash_time + DASH_DURATION > time.elapsed() {
+        velocity.linvel.y = 0.0;
+    } else {
+        end_dash(player, velocity);
+    }
+}
+
+// Jumping cuts a dash short, otherwise the dash would flatten the jump
+fn end_dash(player: &mut Player, velocity: &mut Velocity) {
+    if player.is_dashing {
+        player.is_dashing = false;
+        velocity.linvel.x = velocity.linvel.x.clamp(-player.speed, player.speed);
+    }
+}
+
 pub fn player_butterfly(
     player: &mut Player,
     send_butterfly_event: &mut EventWriter<ButterflyEvent>,
@@ -256,6 +442,7 @@ pub fn player_butterfly(
 
 pub fn player_shoot(
     player: &mut Player,
+    player_entity: Entity,
     transform: &mut Transform,
     send_fire_event: &mut EventWriter<BulletFiredEvent>,
     time: &Res<Time>,
@@ -274,7 +461,14 @@ pub fn player_shoot(
             }
         }
 
+        if player.bullet_kind_until <= time.elapsed() {
+            player.bullet_kind = BulletKind::Normal;
+        }
+
         let event = BulletFiredEvent {
+            shooter: player_entity,
+            player_id: player.id,
+            kind: player.bullet_kind,
             position: bullet_pos,
             direction: player.facing_direction,
         };
@@ -410,13 +604,16 @@ fn draw_mirrors(
 }
 
 pub fn player_controller(
+    mut commands: Commands,
     keyboard_input: Res<Input<KeyCode>>,
     mut players: Query<(
         Entity,
+        &PlayerInput,
         &mut Player,
         &mut Velocity,
         &mut Transform,
         &mut TextureAtlasSprite,
+        Option<&Invulnerable>,
     )>,
     mut send_fire_event: EventWriter<BulletFiredEvent>,
     time: Res<Time>,
@@ -425,28 +622,66 @@ pub fn player_controller(
     mut send_mirror_spawn_event: EventWriter<MirrorSpawnEvent>,
     mut send_butterfly_event: EventWriter<ButterflyEvent>,
 ) {
-    for (entity, mut player, mut velocity, mut transform, mut sprite) in players.iter_mut() {
+    for (entity, input, mut player, mut velocity, mut transform, mut sprite, invulnerable) in
+        players.iter_mut()
+    {
         player.is_running = false;
-        if keyboard_input.pressed(player.key_bindings.left) {
+        let steering_locked = player.last_wall_jump_time + WALL_JUMP_STEERING_LOCK > time.elapsed();
+        if input.pressed(PlayerAction::Left) && !steering_locked {
             player_go_left(&mut player, &mut velocity, &mut sprite);
         }
-        if keyboard_input.pressed(player.key_bindings.right) {
+        if input.pressed(PlayerAction::Right) && !steering_locked {
             player_go_right(&mut player, &mut velocity, &mut sprite);
         }
-        if keyboard_input.pressed(player.key_bindings.jump) {
-            player_jump(&mut player, &mut velocity);
+        player_wall_slide(&player, &mut velocity, input);
+        player_glide(&player, &mut velocity, input);
+        if input.just_pressed(PlayerAction::Jump) {
+            if input.pressed(PlayerAction::Down) {
+                commands
+                    .entity(entity)
+                    .insert(DroppingThrough(Timer::from_seconds(
+                        DROP_THROUGH_SECS,
+                        TimerMode::Once,
+                    )));
+            } else {
+                player.last_jump_press_time = Some(time.elapsed());
+            }
         }
-        if keyboard_input.pressed(player.key_bindings.shoot) {
-            player_shoot(&mut player, &mut transform, &mut send_fire_event, &time);
+        if input.just_released(PlayerAction::Jump) {
+            player_release_jump(&mut player, &mut velocity);
+        }
+        player_jump(&mut player, &mut velocity, &time);
+        player_wall_jump(&mut player, &mut velocity, &mut sprite, &time);
+        player_air_jump(&mut player, &mut velocity, &time);
+        if input.pressed(PlayerAction::Shoot) {
+            player_shoot(
+                &mut player,
+                entity,
+                &mut transform,
+                &mut send_fire_event,
+                &time,
+            );
         }
 
-        if keyboard_input.just_pressed(player.key_bindings.butterfly) {
+        player_update_dash(&mut player, &mut velocity, &time);
+        if input.just_pressed(PlayerAction::Dash) {
+            player_dash(
+                &mut player,
+                &mut velocity,
+                entity,
+                invulnerable.is_some(),
+                &mut commands,
+                &time,
+            );
+        }
+
+        if input.just_pressed(PlayerAction::Butterfly) {
             player_butterfly(&mut player, &mut send_butterfly_event, &time);
         }
-        if keyboard_input.just_pressed(player.key_bindings.powerup) {
+        if input.just_pressed(PlayerAction::Powerup) {
             player_powerup_press(&mut player, entity, &mut send_mirror_spawn_event)
         }
-        if keyboard_input.just_released(player.key_bindings.powerup) {
+        if input.just_released(PlayerAction::Powerup) {
             player_powerup_release(&mut player, entity, &mut send_mirror_use_event)
         }
     }
@@ -455,36 +690,130 @@ pub fn player_controller(
     }
 }
 
-pub fn jump_reset(
-    mut query: Query<(Entity, &mut Player)>,
-    mut platforms: Query<(Entity, &Platform)>,
-    mut contact_events: EventReader<CollisionEvent>,
+// Normals of the surfaces an entity is touching, pointing away from the surface
+pub fn contact_normals(
+    rapier_context: &RapierContext,
+    entity: Entity,
+    is_surface: impl Fn(Entity) -> bool,
+) -> Vec<Vec2> {
+    let mut normals = vec![];
+    for contact_pair in rapier_context.contacts_with(entity) {
+        if !contact_pair.has_any_active_contacts() {
+            continue;
+        }
+        // Manifold normals point from the first collider towards the second
+        let (other, sign) = if contact_pair.collider1() == entity {
+            (contact_pair.collider2(), -1.0)
+        } else {
+            (contact_pair.collider1(), 1.0)
+        };
+        if !is_surface(other) {
+            continue;
+        }
+        for manifold in contact_pair.manifolds() {
+            // Contacts dropped by one-way platforms don't hold the player up
+            if manifold.num_solver_contacts() > 0 {
+                normals.push(manifold.normal() * sign);
+            }
+        }
+    }
+    normals
+}
+
+pub fn update_grounded(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut players: Query<(Entity, &Velocity, &mut Player)>,
+    platforms: Query<(), With<Platform>>,
 ) {
-    for contact_event in contact_events.read() {
-        for (player_entity, mut player) in query.iter_mut() {
-            if let CollisionEvent::Started(h1, h2, _) = contact_event {
-                if let Ok((platform_entity, platform)) = platforms.get(*h1).or(platforms.get(*h2)) {
-                    if h1 == &player_entity || h2 == &player_entity {
-                        player.is_jumping = false
-                    }
-                }
+    for (entity, velocity, mut player) in players.iter_mut() {
+        let normals = contact_normals(&rapier_context, entity, |other| platforms.contains(other));
+        player.grounded = normals.iter().any(|normal| normal.y > GROUND_NORMAL_MIN_Y);
+        // Steep slopes left by reflections count as walls too
+        player.wall_normal = normals
+            .iter()
+            .copied()
+            .filter(|normal| normal.y.abs() <= GROUND_NORMAL_MIN_Y)
+            .max_by(|a, b| a.x.abs().total_cmp(&b.x.abs()));
+
+        if player.grounded {
+            player.last_grounded_time = time.elapsed();
+            player.air_jump_available = true;
+            // Still touching the ground on the frame a jump starts
+            if velocity.linvel.y <= 0.0 {
+                player.is_jumping = false;
             }
         }
     }
 }
 
+// Keeps falling through until clear of every one-way platform
+fn tick_dropping_through(
+    mut commands: Commands,
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut players: Query<(Entity, &mut DroppingThrough)>,
+    platforms: Query<&Platform>,
+) {
+    for (entity, mut dropping) in players.iter_mut() {
+        dropping.0.tick(time.delta());
+        if !dropping.0.finished() {
+            continue;
+        }
+
+        let inside_one_way = rapier_context.contacts_with(entity).any(|contact_pair| {
+            contact_pair.has_any_active_contacts()
+                && [contact_pair.collider1(), contact_pair.collider2()]
+                    .into_iter()
+                    .any(|other| {
+                        platforms
+                            .get(other)
+                            .is_ok_and(|platform| platform.one_way.is_some())
+                    })
+        });
+        if !inside_one_way {
+            commands.entity(entity).remove::<DroppingThrough>();
+        }
+    }
+}
+
 fn check_death_collision(
-    mut players: Query<(&Transform, &Player)>,
+    mut commands: Commands,
+    mut players: Query<(Entity, &mut Transform, &mut Velocity, &mut Player)>,
     //mut death_zones: Query<(Entity, &DeathZone)>,
     //mut contact_events: EventReader<CollisionEvent>,
-    mut send_game_over_event: EventWriter<GameOverEvent>,
     map: Res<MapDescription>,
 ) {
-    for (transform, player) in players.iter() {
-        if transform.translation.y < map.death_zone {
-            send_game_over_event.send(GameOverEvent {
-                lost_player: player.id,
-            })
+    let mut killers = vec![];
+    for (entity, mut transform, mut velocity, mut player) in players.iter_mut() {
+        if transform.translation.y >= map.death_zone || player.lives == 0 {
+            continue;
+        }
+
+        // Knocking yourself out doesn't count
+        killers.extend(player.last_hit_by.take().filter(|killer| *killer != entity));
+        player.lives -= 1;
+        if player.lives == 0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let spawn_point = map.spawn_point(player.id as usize);
+        transform.translation = spawn_point.position.extend(transform.translation.z);
+        velocity.linvel = Vec2::ZERO;
+        player.is_jumping = false;
+        player.damage = 0.0;
+        player.bullet_kind = BulletKind::Normal;
+        commands
+            .entity(entity)
+            .insert(Invulnerable(Timer::from_seconds(
+                RESPAWN_INVULNERABILITY_SECS,
+                TimerMode::Once,
+            )));
+    }
+    for killer in killers {
+        if let Ok((_, _, _, mut player)) = players.get_mut(killer) {
+            player.kills += 1;
         }
     }
     // for contact_event in contact_events.read() {
@@ -502,6 +831,49 @@ fn check_death_collision(
     // }
 }
 
+fn reset_match_decided(mut decided: ResMut<MatchDecided>) {
+    decided.0 = false;
+}
+
+fn check_last_player_standing(
+    players: Query<&Player>,
+    mut decided: ResMut<MatchDecided>,
+    mut send_game_over_event: EventWriter<GameOverEvent>,
+) {
+    if decided.0 {
+        return;
+    }
+    let mut remaining = players.iter().filter(|player| player.lives > 0);
+    let winner = remaining.next();
+
+    // Teammates win together
+    if remaining.all(|player| Some(player.team) == winner.map(|winner| winner.team)) {
+        send_game_over_event.send(GameOverEvent {
+            winner: winner.map(|player| player.id),
+        });
+        decided.0 = true;
+    }
+}
+
+fn tick_invulnerability(
+    mut commands: Commands,
+    mut players: Query<(Entity, &mut Invulnerable, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, mut sprite) in &mut players {
+        invulnerable.0.tick(time.delta());
+
+        if invulnerable.0.finished() {
+            sprite.color.set_a(1.0);
+            commands.entity(entity).remove::<Invulnerable>();
+        } else {
+            // Blink while invulnerable
+            let visible = (invulnerable.0.elapsed_secs() * 10.0) as u32 % 2 == 0;
+            sprite.color.set_a(if visible { 1.0 } else { 0.3 });
+        }
+    }
+}
+
 fn reflect_player_through_point(mut transform: Transform, reflection_point: Transform) {
     let pos = transform.translation;
     let reflection_pos = reflection_point.translation;
@@ -5,14 +5,22 @@ use bevy_rapier2d::{
     geometry::{ActiveEvents, Collider},
     pipeline::CollisionEvent,
 };
-use rand::prelude::*;
+use rand::{distributions::WeightedIndex, prelude::*};
 
 use crate::{
     game::{DespawnOnRestart, LifeTimer, MirrorType},
     AppState,
 };
 
-use super::{Player, PlayerSpawnEvent, Powerup, PowerupState};
+use super::{BulletKind, MapDescription, Player, Powerup, PowerupState, PowerupTracker};
+
+const AMMO_DURATION: Duration = Duration::from_secs(10);
+const AMMO_KINDS: [BulletKind; 4] = [
+    BulletKind::Bouncing,
+    BulletKind::Piercing,
+    BulletKind::Spread,
+    BulletKind::Heavy,
+];
 
 pub struct PowerupsPlugin;
 
@@ -20,10 +28,6 @@ impl Plugin for PowerupsPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<PowerupSpawnEvent>()
             .add_event::<PowerupCollectionEvent>()
-            .add_systems(
-                OnEnter(AppState::InGame),
-                spawn_powerup_trackers.after(super::player::spawn_players),
-            )
             .add_systems(
                 Update,
                 check_powerup_color.run_if(in_state(AppState::InGame)),
@@ -50,10 +54,18 @@ impl Plugin for PowerupsPlugin {
 #[derive(Event)]
 struct PowerupSpawnEvent {}
 
-const POWERUP_XMIN: f32 = -20.0;
-const POWERUP_XMAX: f32 = 20.0;
-const POWERUP_YMIN: f32 = -5.0;
-const POWERUP_YMAX: f32 = 20.0;
+// Larger regions are proportionally more likely to get the powerup
+fn random_point_in_regions(regions: &[Rect], rng: &mut impl Rng) -> Option<Vec2> {
+    let weights = WeightedIndex::new(
+        regions
+            .iter()
+            .map(|region| region.width() * region.height()),
+    )
+    .ok()?;
+    let region = regions[weights.sample(rng)];
+
+    Some(region.min + Vec2::new(rng.gen(), rng.gen()) * region.size())
+}
 
 fn spawn_powerup(
     mut commands: Commands,
@@ -62,12 +74,14 @@ fn spawn_powerup(
     mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
     time: Res<Time>,
+    map: Res<MapDescription>,
 ) {
     for _ in powerup_event.read() {
         let mut rng = thread_rng();
 
-        let x = POWERUP_XMIN + rng.gen::<f32>() * (POWERUP_XMAX - POWERUP_XMIN);
-        let y = POWERUP_YMIN + rng.gen::<f32>() * (POWERUP_YMAX - POWERUP_YMIN);
+        let Some(Vec2 { x, y }) = random_point_in_regions(&map.powerup_regions, &mut rng) else {
+            continue;
+        };
 
         let mover = PowerupMover {
             speed: 0.25 + 1.5 * rng.gen::<f32>(),
@@ -84,12 +98,32 @@ fn spawn_powerup(
 
         info!("Spawning Powerup at {:?} {:?}", x, y);
 
-        let reflections = thread_rng().gen_range(1..8);
+        // Half of the powerups are mirrors, the others change the player's bullets
+        let powerup = if rng.gen_bool(0.5) {
+            let reflections = thread_rng().gen_range(1..8);
+            Powerup::Mirror(MirrorType {
+                reflect_bullets: (reflections & 1) > 0,
+                reflect_players: (reflections & 2) > 0,
+                reflect_platforms: (reflections & 4) > 0,
+            })
+        } else {
+            Powerup::Ammo(AMMO_KINDS[rng.gen_range(0..AMMO_KINDS.len())])
+        };
+
+        let texture = asset_server.load("textures/orb.png");
+        let material = match powerup {
+            // Tinted like the bullets it gives
+            Powerup::Ammo(kind) => ColorMaterial {
+                color: kind.color().unwrap_or(Color::WHITE),
+                texture: Some(texture),
+            },
+            Powerup::Mirror(_) => texture.into(),
+        };
 
         commands.spawn((
             MaterialMesh2dBundle {
                 mesh: meshes.add(shape::Circle::new(0.5).into()).into(),
-                material: materials.add(asset_server.load("textures/orb.png").into()),
+                material: materials.add(material),
                 transform: Transform::from_translation(
                     mover.get_position(time.elapsed_seconds()).extend(0.0),
                 ),
@@ -99,11 +133,7 @@ fn spawn_powerup(
             ActiveEvents::COLLISION_EVENTS,
             DespawnOnRestart {},
             LifeTimer(Timer::from_seconds(10.0, TimerMode::Once)),
-            Powerup::Mirror(MirrorType {
-                reflect_bullets: (reflections & 1) > 0,
-                reflect_players: (reflections & 2) > 0,
-                reflect_platforms: (reflections & 4) > 0,
-            }),
+            powerup,
             mover,
         ));
     }
@@ -120,6 +150,7 @@ fn handle_powerup_collection(
     mut collection_events: EventReader<PowerupCollectionEvent>,
     mut players: Query<(Entity, &mut Player)>,
     powerups: Query<(Entity, &Powerup)>,
+    time: Res<Time>,
 ) {
     for PowerupCollectionEvent {
         player_entity,
@@ -130,12 +161,18 @@ fn handle_powerup_collection(
             if let Ok((_, powerup)) = powerups.get(*powerup_entity) {
                 commands.entity(*powerup_entity).despawn();
 
-                player.powerup = Some(match *powerup {
-                    Powerup::Mirror(mirror_type) => PowerupState::Mirror {
-                        r#type: mirror_type,
-                        placed: false,
-                    },
-                });
+                match *powerup {
+                    Powerup::Mirror(mirror_type) => {
+                        player.powerup = Some(PowerupState::Mirror {
+                            r#type: mirror_type,
+                            placed: false,
+                        })
+                    }
+                    Powerup::Ammo(kind) => {
+                        player.bullet_kind = kind;
+                        player.bullet_kind_until = time.elapsed() + AMMO_DURATION;
+                    }
+                }
             }
         }
     }
@@ -174,11 +211,6 @@ fn check_powerup_spawn(
     }
 }
 
-#[derive(Component)]
-struct PowerupTracker {
-    player: Entity,
-}
-
 fn get_powerup_color(state: Option<PowerupState>) -> Color {
     match state {
         None => Color::NONE,
@@ -202,39 +234,6 @@ fn check_powerup_color(
     });
 }
 
-fn spawn_powerup_trackers(
-    mut commands: Commands,
-    mut player_spawn_event: EventReader<PlayerSpawnEvent>,
-) {
-    for PlayerSpawnEvent { player_id, player } in player_spawn_event.read() {
-        info!("Creating for player {:?}", player_id);
-
-        let mut style = Style {
-            width: Val::VMin(5.0),
-            height: Val::VMin(5.0),
-            position_type: PositionType::Absolute,
-            bottom: Val::Percent(5.0),
-            ..default()
-        };
-
-        if *player_id == 0 {
-            style.left = Val::Percent(7.5);
-        } else {
-            style.right = Val::Percent(7.5);
-        }
-
-        commands.spawn((
-            NodeBundle {
-                style,
-                background_color: Color::NONE.into(),
-                ..default()
-            },
-            PowerupTracker { player: *player },
-            DespawnOnRestart {},
-        ));
-    }
-}
-
 enum Shape {
     None,
     Circle,
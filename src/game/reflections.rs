@@ -4,13 +4,13 @@ use bevy::prelude::*;
 use bevy_rapier2d::dynamics::Velocity;
 
 use crate::{
-    geometry::{utils::signed_area, Circle, Croppable, Line, LineSegment, Reflectable},
+    geometry::{utils::signed_area, Circle, Contour, Croppable, Line, LineSegment, Reflectable},
     AppState,
 };
 
 use super::{
-    spawn_polygon, Bullet, DespawnOnRestart, Materials, MirrorAnimation, MirrorType, Platform,
-    Player,
+    spawn_contour, Bullet, DespawnOnRestart, FlattenTolerance, Materials, MirrorAnimation,
+    MirrorType, Platform, Player,
 };
 
 pub struct ReflectionsPlugin;
@@ -50,37 +50,44 @@ fn mirror_reflect_platforms(
     platforms: Query<(Entity, &Transform, &Platform)>,
     materials: Res<Materials>,
     mut meshes: ResMut<Assets<Mesh>>,
+    tolerance: Res<FlattenTolerance>,
 ) {
     for PlatformsMirrorReflectionEvent { mirror } in reflection_event_reader.read() {
         for (entity, transform, platform) in platforms.iter() {
-            let polygon = platform.get_transformed_polygon(transform);
+            let contour = platform.get_transformed_contour(transform);
             let (a, b) = mirror.endpoints();
             let mirror_line = mirror.get_line();
             let border_a = mirror_line.perpendicular_through(a);
             let border_b = mirror_line.perpendicular_through(b);
 
-            if let Some(middle) = polygon
+            if let Some(middle) = contour
                 .crop_to_halfplane(border_a, border_a.side(b))
-                .and_then(|polygon| polygon.crop_to_halfplane(border_b, border_b.side(a)))
+                .and_then(|contour| contour.crop_to_halfplane(border_b, border_b.side(a)))
             {
                 let sides = [
-                    polygon.crop_to_halfplane(border_a, -border_a.side(b)),
-                    polygon.crop_to_halfplane(border_b, -border_b.side(a)),
+                    contour.crop_to_halfplane(border_a, -border_a.side(b)),
+                    contour.crop_to_halfplane(border_b, -border_b.side(a)),
                 ];
 
                 commands.entity(entity).despawn();
                 for poly in sides.into_iter().flatten() {
-                    spawn_polygon(
+                    spawn_contour(
                         Vec2::new(0.0, 0.0),
                         poly,
+                        platform.one_way,
+                        tolerance.0,
                         &mut commands,
                         &materials,
                         &mut meshes,
                     );
                 }
-                spawn_polygon(
+                spawn_contour(
                     Vec2::new(0.0, 0.0),
                     middle.reflect_over_line(mirror_line),
+                    platform.one_way.map(|up| {
+                        map_direction(up, a, |pt| Some(pt.reflect_over_line(mirror_line)))
+                    }),
+                    tolerance.0,
                     &mut commands,
                     &materials,
                     &mut meshes,
@@ -103,6 +110,7 @@ fn invert_platforms(
     platforms: Query<(Entity, &Transform, &Platform)>,
     materials: Res<Materials>,
     mut meshes: ResMut<Assets<Mesh>>,
+    tolerance: Res<FlattenTolerance>,
 ) {
     for PlatformsInversionEvent {
         circle,
@@ -112,7 +120,7 @@ fn invert_platforms(
     {
         dbg!("Running inversion");
         for (entity, transform, platform) in platforms.iter() {
-            let polygon = platform.get_transformed_polygon(transform);
+            let contour = platform.get_transformed_contour(transform);
             let line_a = Line::new_through(circle.center(), circle.center() + *angle_start);
             let dir_b = Vec2::from_angle(*angle_len).rotate(*angle_start);
             let line_b = Line::new_through(circle.center(), circle.center() + dir_b);
@@ -120,8 +128,8 @@ fn invert_platforms(
             let a_inside = Vec2::from_angle(PI / 2.0).rotate(*angle_start);
             let b_inside = Vec2::from_angle(-PI / 2.0).rotate(dir_b);
 
-            let temp_1 = polygon.crop_to_halfplane(line_a, line_a.side(a_inside));
-            let temp_2 = polygon.crop_to_halfplane(line_a, -line_a.side(a_inside));
+            let temp_1 = contour.crop_to_halfplane(line_a, line_a.side(a_inside));
+            let temp_2 = contour.crop_to_halfplane(line_a, -line_a.side(a_inside));
 
             let both_inside = temp_1
                 .as_ref()
@@ -143,19 +151,24 @@ fn invert_platforms(
                         .into_iter()
                         .flatten()
                     {
-                        spawn_polygon(
+                        spawn_contour(
                             Vec2::new(0.0, 0.0),
                             poly,
+                            platform.one_way,
+                            tolerance.0,
                             &mut commands,
                             &materials,
                             &mut meshes,
                         );
                     }
 
+                    let one_way = invert_direction(platform.one_way, &both, *circle, tolerance.0);
                     if let Some(inverted) = both.invert_over_circle(*circle) {
-                        spawn_polygon(
+                        spawn_contour(
                             Vec2::new(0.0, 0.0),
                             inverted,
+                            one_way,
+                            tolerance.0,
                             &mut commands,
                             &materials,
                             &mut meshes,
@@ -169,9 +182,11 @@ fn invert_platforms(
                 commands.entity(entity).despawn();
 
                 if let Some(outside) = both_outside {
-                    spawn_polygon(
+                    spawn_contour(
                         Vec2::new(0.0, 0.0),
                         outside,
+                        platform.one_way,
+                        tolerance.0,
                         &mut commands,
                         &materials,
                         &mut meshes,
@@ -182,10 +197,13 @@ fn invert_platforms(
                     .into_iter()
                     .flatten()
                 {
+                    let one_way = invert_direction(platform.one_way, &poly, *circle, tolerance.0);
                     if let Some(new_poly) = poly.invert_over_circle(*circle) {
-                        spawn_polygon(
+                        spawn_contour(
                             Vec2::new(0.0, 0.0),
                             new_poly,
+                            one_way,
+                            tolerance.0,
                             &mut commands,
                             &materials,
                             &mut meshes,
@@ -197,6 +215,26 @@ fn invert_platforms(
     }
 }
 
+// Where a direction at a point ends up when the point is moved by the given map
+fn map_direction(direction: Vec2, at: Vec2, map: impl Fn(Vec2) -> Option<Vec2>) -> Vec2 {
+    const STEP: f32 = 0.01;
+    match (map(at), map(at + direction * STEP)) {
+        (Some(a), Some(b)) => (b - a).normalize_or_zero(),
+        _ => direction,
+    }
+}
+
+// Inversion bends the platform, so take its new up direction at the middle of the piece
+fn invert_direction(
+    one_way: Option<Vec2>,
+    piece: &Contour,
+    circle: Circle,
+    tolerance: f32,
+) -> Option<Vec2> {
+    let (min, max) = piece.flatten(tolerance).bounding_box();
+    one_way.map(|up| map_direction(up, (min + max) / 2.0, |pt| pt.invert_over_circle(circle)))
+}
+
 #[derive(Event)]
 pub struct PlayerInversionEvent {
     pub circle: Circle,
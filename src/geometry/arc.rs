@@ -0,0 +1,146 @@
+use std::f32::consts::PI;
+
+use super::utils::{cross, EPS};
+use super::{Circle, Curve, Line, LineSegment, Point};
+
+// Arcs bulging less than this from their chord are treated as straight
+const MIN_SAGITTA: f32 = 1e-4;
+const MAX_SUBDIVISIONS: usize = 256;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Arc {
+    circle: Circle,
+    start_angle: f32,
+    sweep: f32,
+}
+
+impl Arc {
+    pub fn new(circle: Circle, start_angle: f32, sweep: f32) -> Arc {
+        Arc {
+            circle,
+            start_angle,
+            sweep,
+        }
+    }
+
+    pub fn through(start: Point, via: Point, end: Point) -> Curve {
+        let chord = end - start;
+        let d = 2.0 * cross(via - start, chord);
+
+        if chord.length() < EPS || d.abs() / chord.length() < 2.0 * MIN_SAGITTA {
+            return Curve::Segment(LineSegment::new(start, end));
+        }
+
+        let (ab, ac) = (via - start, chord);
+        let center = start
+            + Point::new(
+                ab.length_squared() * ac.y - ac.length_squared() * ab.y,
+                ac.length_squared() * ab.x - ab.length_squared() * ac.x,
+            ) / d;
+
+        let start_angle = angle_of(start - center);
+        let mut sweep = angle_of(end - center) - start_angle;
+        if d > 0.0 {
+            // start -> via -> end turns left, so the arc runs counter-clockwise
+            sweep = sweep.rem_euclid(2.0 * PI);
+        } else {
+            sweep = -(-sweep).rem_euclid(2.0 * PI);
+        }
+
+        Curve::Arc(Arc::new(
+            Circle::new(center, (start - center).length()),
+            start_angle,
+            sweep,
+        ))
+    }
+
+    pub fn point_at(&self, t: f32) -> Point {
+        self.circle.center()
+            + Point::from_angle(self.start_angle + self.sweep * t) * self.circle.radius()
+    }
+
+    pub fn start(&self) -> Point {
+        self.point_at(0.0)
+    }
+
+    pub fn end(&self) -> Point {
+        self.point_at(1.0)
+    }
+
+    pub fn mid_point(&self) -> Point {
+        self.point_at(0.5)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.circle.radius() * self.sweep.abs()
+    }
+
+    pub fn reverse(&self) -> Arc {
+        Arc::new(self.circle, self.start_angle + self.sweep, -self.sweep)
+    }
+
+    pub fn sub_arc(&self, t0: f32, t1: f32) -> Arc {
+        Arc::new(
+            self.circle,
+            self.start_angle + self.sweep * t0,
+            self.sweep * (t1 - t0),
+        )
+    }
+
+    pub fn subdivisions(&self, tolerance: f32) -> usize {
+        let radius = self.circle.radius();
+        let max_step = 2.0 * (1.0 - (tolerance / radius).min(1.0)).acos();
+
+        ((self.sweep.abs() / max_step).ceil() as usize).clamp(1, MAX_SUBDIVISIONS)
+    }
+
+    pub fn intersect_line(&self, line: Line) -> Vec<f32> {
+        let center = self.circle.center();
+        let radius = self.circle.radius();
+        let dist = line.side(center);
+
+        if dist.abs() > radius {
+            return vec![];
+        }
+
+        let foot = center - dist * line.normal();
+        let half_chord = (radius * radius - dist * dist).max(0.0).sqrt();
+
+        let mut params: Vec<f32> = [-half_chord, half_chord]
+            .into_iter()
+            .map(|offset| self.param_of(foot + offset * line.direction()))
+            .filter(|t| EPS < *t && *t < 1.0 - EPS)
+            .collect();
+        params.sort_by(f32::total_cmp);
+        params.dedup_by(|a, b| (*a - *b).abs() < EPS);
+
+        params
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        let t = self.param_of(p);
+        ((p - self.circle.center()).length() - self.circle.radius()).abs() < EPS
+            && -EPS <= t
+            && t <= 1.0 + EPS
+    }
+
+    fn param_of(&self, p: Point) -> f32 {
+        let delta = angle_of(p - self.circle.center()) - self.start_angle;
+
+        if self.sweep > 0.0 {
+            delta.rem_euclid(2.0 * PI) / self.sweep
+        } else {
+            -(-delta).rem_euclid(2.0 * PI) / self.sweep
+        }
+    }
+
+    // Contribution of the arc to the shoelace sum of a closed boundary
+    pub fn area_term(&self) -> f32 {
+        let radius = self.circle.radius();
+        cross(self.circle.center(), self.end() - self.start()) + radius * radius * self.sweep
+    }
+}
+
+fn angle_of(p: Point) -> f32 {
+    p.y.atan2(p.x)
+}
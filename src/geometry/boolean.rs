@@ -0,0 +1,303 @@
+use std::f32::consts::PI;
+
+use super::utils::{cross, signed_area, EPS};
+use super::{Croppable, Line, Point, Polygon};
+
+// Clip polygons are nudged by this much when the inputs touch in a degenerate way
+const PERTURBATION: f32 = 1e-4;
+const PERTURBATION_DIRECTIONS: usize = 8;
+const DEGENERACY_TOLERANCE: f32 = 1e-5;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl Operation {
+    // Whether an edge of one input belongs to the result, given if it lies inside the other input
+    fn keeps(self, first: bool, inside_other: bool) -> bool {
+        match self {
+            Operation::Union => !inside_other,
+            Operation::Intersection => inside_other,
+            Operation::Difference => inside_other != first,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    point: Point,
+    texture_coord: Point,
+    neighbor: Option<usize>,
+    visited: bool,
+}
+
+impl Polygon {
+    // Holes can't be represented by a Polygon, so they are filled in
+    pub fn union(&self, other: &Polygon) -> Vec<Polygon> {
+        boolean(self, other, Operation::Union)
+    }
+
+    pub fn intersection(&self, other: &Polygon) -> Vec<Polygon> {
+        boolean(self, other, Operation::Intersection)
+    }
+
+    pub fn difference(&self, other: &Polygon) -> Vec<Polygon> {
+        boolean(self, other, Operation::Difference)
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        let n = self.num_vertices();
+        let vertices = self.vertices();
+        let mut inside = false;
+
+        for i in 0..n {
+            let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                inside = !inside;
+            }
+        }
+
+        inside
+    }
+
+    fn translated(&self, offset: Point) -> Polygon {
+        Polygon::new(
+            self.vertices().iter().map(|p| *p + offset).collect(),
+            self.texture_coords().clone(),
+        )
+    }
+
+    fn interior_point(&self) -> Point {
+        match self.triangulate().first() {
+            Some([a, b, c]) => {
+                (self.vertices()[*a] + self.vertices()[*b] + self.vertices()[*c]) / 3.0
+            }
+            None => self.vertices()[0],
+        }
+    }
+}
+
+fn boolean(a: &Polygon, b: &Polygon, operation: Operation) -> Vec<Polygon> {
+    let offsets = [Point::ZERO]
+        .into_iter()
+        .chain((0..PERTURBATION_DIRECTIONS).map(|k| {
+            Point::from_angle(2.0 * PI * k as f32 / PERTURBATION_DIRECTIONS as f32) * PERTURBATION
+        }));
+
+    let mut candidates = vec![];
+    for offset in offsets {
+        if let Some(result) = clip(a, &b.translated(offset), operation) {
+            // Touching inputs may come apart or fuse depending on the nudge, prefer fusing
+            if offset == Point::ZERO || operation != Operation::Union {
+                return result;
+            }
+            candidates.push(result);
+        }
+    }
+
+    candidates
+        .into_iter()
+        .min_by_key(Vec::len)
+        .unwrap_or_else(|| match operation {
+            Operation::Union => vec![a.clone(), b.clone()],
+            Operation::Intersection => vec![],
+            Operation::Difference => vec![a.clone()],
+        })
+}
+
+// Greiner-Hormann clipping, None if the inputs are degenerate
+fn clip(a: &Polygon, b: &Polygon, operation: Operation) -> Option<Vec<Polygon>> {
+    let (n, m) = (a.num_vertices(), b.num_vertices());
+    let mut crossings = vec![];
+
+    for i in 0..n {
+        let (p, r) = (a.vertices()[i], a.vertices()[(i + 1) % n] - a.vertices()[i]);
+
+        for j in 0..m {
+            let (q, s) = (b.vertices()[j], b.vertices()[(j + 1) % m] - b.vertices()[j]);
+            let denom = cross(r, s);
+
+            if denom.abs() < EPS {
+                if Line::new_through(p, p + r).distance(q) < DEGENERACY_TOLERANCE
+                    && overlap(p, r, q, s)
+                {
+                    return None;
+                }
+                continue;
+            }
+
+            let t = cross(q - p, s) / denom;
+            let u = cross(q - p, r) / denom;
+            let (t_tol, u_tol) = (
+                DEGENERACY_TOLERANCE / r.length(),
+                DEGENERACY_TOLERANCE / s.length(),
+            );
+
+            if t < -t_tol || t > 1.0 + t_tol || u < -u_tol || u > 1.0 + u_tol {
+                continue;
+            }
+            if t < t_tol || t > 1.0 - t_tol || u < u_tol || u > 1.0 - u_tol {
+                return None;
+            }
+
+            crossings.push((i, j, t, u, p + r * t));
+        }
+    }
+
+    if crossings.is_empty() {
+        return Some(nested(a, b, operation));
+    }
+
+    let (mut list_a, index_a) = build_list(a, &crossings, |(i, _, t, _, _)| (*i, *t));
+    let (mut list_b, index_b) = build_list(b, &crossings, |(_, j, _, u, _)| (*j, *u));
+    for (k, crossing) in crossings.iter().enumerate() {
+        let (_, j, _, u, _) = crossing;
+        list_a[index_a[k]].neighbor = Some(index_b[k]);
+        list_b[index_b[k]].neighbor = Some(index_a[k]);
+        list_b[index_b[k]].texture_coord =
+            b.texture_coords()[*j].lerp(b.texture_coords()[(*j + 1) % m], *u);
+    }
+
+    let mut lists = [list_a, list_b];
+    let polygons = [a, b];
+    let mut result = vec![];
+
+    while let Some(start) = lists[0]
+        .iter()
+        .position(|node| node.neighbor.is_some() && !node.visited)
+    {
+        let mut vertices = vec![];
+        let mut texture_coords = vec![];
+        let (mut side, mut index) = (0, start);
+        let mut reversed = false;
+
+        for _ in 0..(lists[0].len() + lists[1].len()) {
+            let len = lists[side].len();
+            let node = lists[side][index];
+            lists[side][index].visited = true;
+            lists[1 - side][node.neighbor.unwrap()].visited = true;
+
+            let forward = lists[side][(index + 1) % len].point;
+            let inside = polygons[1 - side].contains((node.point + forward) / 2.0);
+            let step = if operation.keeps(side == 0, inside) {
+                1
+            } else {
+                len - 1
+            };
+
+            if side == 0 {
+                reversed = step != 1;
+            }
+
+            vertices.push(node.point);
+            texture_coords.push(node.texture_coord);
+            index = (index + step) % len;
+
+            while lists[side][index].neighbor.is_none() {
+                vertices.push(lists[side][index].point);
+                texture_coords.push(lists[side][index].texture_coord);
+                index = (index + step) % len;
+            }
+
+            index = lists[side][index].neighbor.unwrap();
+            side = 1 - side;
+
+            if lists[side][index].visited {
+                break;
+            }
+        }
+
+        // Walking the first input backwards traces the ring clockwise
+        if reversed {
+            vertices.reverse();
+            texture_coords.reverse();
+        }
+
+        // Rings that still run clockwise are holes
+        if signed_area(&vertices) > 0.0 {
+            result.extend(Polygon::new(vertices, texture_coords).sanitize_polynomial(EPS, 0.01));
+        }
+    }
+
+    Some(result)
+}
+
+fn overlap(p: Point, r: Point, q: Point, s: Point) -> bool {
+    let dir = r.normalize();
+    let (a0, a1) = (0.0, r.length());
+    let (b0, b1) = ((q - p).dot(dir), (q + s - p).dot(dir));
+
+    b0.max(b1) > a0 - DEGENERACY_TOLERANCE && b0.min(b1) < a1 + DEGENERACY_TOLERANCE
+}
+
+type Crossing = (usize, usize, f32, f32, Point);
+
+fn build_list(
+    polygon: &Polygon,
+    crossings: &[Crossing],
+    key: impl Fn(&Crossing) -> (usize, f32),
+) -> (Vec<Node>, Vec<usize>) {
+    let n = polygon.num_vertices();
+    let mut nodes = vec![];
+    let mut index = vec![0; crossings.len()];
+
+    for i in 0..n {
+        nodes.push(Node {
+            point: polygon.vertices()[i],
+            texture_coord: polygon.texture_coords()[i],
+            neighbor: None,
+            visited: false,
+        });
+
+        let mut on_edge: Vec<usize> = (0..crossings.len())
+            .filter(|k| key(&crossings[*k]).0 == i)
+            .collect();
+        on_edge.sort_by(|k, l| key(&crossings[*k]).1.total_cmp(&key(&crossings[*l]).1));
+
+        for k in on_edge {
+            let t = key(&crossings[k]).1;
+            index[k] = nodes.len();
+            nodes.push(Node {
+                point: crossings[k].4,
+                texture_coord: polygon.texture_coords()[i]
+                    .lerp(polygon.texture_coords()[(i + 1) % n], t),
+                neighbor: None,
+                visited: false,
+            });
+        }
+    }
+
+    (nodes, index)
+}
+
+// Result when the boundaries don't cross, so one input is inside the other or they are apart
+fn nested(a: &Polygon, b: &Polygon, operation: Operation) -> Vec<Polygon> {
+    let a_in_b = b.contains(a.vertices()[0]);
+    let b_in_a = a.contains(b.vertices()[0]);
+
+    match operation {
+        Operation::Union if a_in_b => vec![b.clone()],
+        Operation::Union if b_in_a => vec![a.clone()],
+        Operation::Union => vec![a.clone(), b.clone()],
+        Operation::Intersection if a_in_b => vec![a.clone()],
+        Operation::Intersection if b_in_a => vec![b.clone()],
+        Operation::Intersection => vec![],
+        Operation::Difference if a_in_b => vec![],
+        Operation::Difference if b_in_a => {
+            // Cut around the hole so every piece is a simple polygon again
+            let center = b.interior_point();
+            let cut = Line::new_through(center, center + Point::Y);
+
+            [1.0, -1.0]
+                .into_iter()
+                .filter_map(|side| a.crop_to_halfplane(cut, side))
+                .flat_map(|half| half.difference(b))
+                .collect()
+        }
+        Operation::Difference => vec![a.clone()],
+    }
+}
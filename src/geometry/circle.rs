@@ -1,4 +1,5 @@
-use super::Point;
+use super::utils::EPS;
+use super::{Line, Point, Reflectable};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Circle {
@@ -23,3 +24,31 @@ impl Circle {
         self.radius
     }
 }
+
+impl Reflectable for Circle {
+    // None when the circle passes through the center and becomes a line
+    type InvertOutType = Option<Circle>;
+
+    fn reflect_over_point(&self, origin: Point) -> Self {
+        Circle::new(self.center.reflect_over_point(origin), self.radius)
+    }
+
+    fn reflect_over_line(&self, line: Line) -> Self {
+        Circle::new(self.center.reflect_over_line(line), self.radius)
+    }
+
+    fn invert_over_circle(&self, circle: Circle) -> Self::InvertOutType {
+        let diff = self.center - circle.center();
+        let power = diff.length_squared() - self.radius * self.radius;
+
+        if power.abs() < EPS {
+            return None;
+        }
+
+        let scale = circle.radius() * circle.radius() / power;
+        Some(Circle::new(
+            circle.center() + diff * scale,
+            self.radius * scale.abs(),
+        ))
+    }
+}
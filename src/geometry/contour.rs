@@ -0,0 +1,236 @@
+use super::utils::EPS;
+use super::{Arc, Circle, Croppable, Curve, Line, LineSegment, Point, Polygon, Reflectable};
+
+// A closed boundary made of line segments and circular arcs. Texture coordinates
+// are given at the start of every curve and interpolated along it.
+#[derive(Debug, Clone)]
+pub struct Contour {
+    curves: Vec<Curve>,
+    texture_coords: Vec<Point>,
+}
+
+impl Contour {
+    pub fn new(curves: Vec<Curve>, texture_coords: Vec<Point>) -> Contour {
+        let contour = Contour {
+            curves,
+            texture_coords,
+        };
+
+        if contour.area() < 0.0 {
+            contour.reversed()
+        } else {
+            contour
+        }
+    }
+
+    pub fn curves(&self) -> &Vec<Curve> {
+        &self.curves
+    }
+
+    pub fn num_curves(&self) -> usize {
+        self.curves.len()
+    }
+
+    pub fn area(&self) -> f32 {
+        self.curves.iter().map(Curve::area_term).sum::<f32>() / 2.0
+    }
+
+    pub fn texture_coord_at(&self, index: usize, t: f32) -> Point {
+        let n = self.num_curves();
+        self.texture_coords[index].lerp(self.texture_coords[(index + 1) % n], t)
+    }
+
+    pub fn map_points(&self, f: impl Fn(Point) -> Point) -> Contour {
+        Contour::new(
+            self.curves
+                .iter()
+                .map(|curve| match curve {
+                    Curve::Segment(seg) => {
+                        let (a, b) = seg.endpoints();
+                        Curve::Segment(LineSegment::new(f(a), f(b)))
+                    }
+                    Curve::Arc(_) => {
+                        Arc::through(f(curve.start()), f(curve.mid_point()), f(curve.end()))
+                    }
+                })
+                .collect(),
+            self.texture_coords.clone(),
+        )
+    }
+
+    fn reversed(&self) -> Contour {
+        let n = self.num_curves();
+
+        Contour {
+            curves: self.curves.iter().rev().map(Curve::reverse).collect(),
+            texture_coords: (0..n).map(|i| self.texture_coords[(n - i) % n]).collect(),
+        }
+    }
+
+    pub fn sanitize(&self, min_length: f32, min_area: f32) -> Option<Contour> {
+        let mut curves = vec![];
+        let mut texture_coords = vec![];
+
+        for (curve, texture_coord) in self.curves.iter().zip(self.texture_coords.iter()) {
+            if curve.length() >= min_length {
+                curves.push(*curve);
+                texture_coords.push(*texture_coord);
+            }
+        }
+
+        let contour = Contour {
+            curves,
+            texture_coords,
+        };
+
+        if contour.num_curves() > 1 && contour.area().abs() > min_area {
+            Some(contour)
+        } else {
+            None
+        }
+    }
+
+    pub fn flatten(&self, tolerance: f32) -> Polygon {
+        let mut vertices = vec![];
+        let mut texture_coords = vec![];
+
+        for (index, curve) in self.curves.iter().enumerate() {
+            let n = curve.subdivisions(tolerance);
+
+            for step in 0..n {
+                let t = step as f32 / n as f32;
+                vertices.push(curve.point_at(t));
+                texture_coords.push(self.texture_coord_at(index, t));
+            }
+        }
+
+        Polygon::new(vertices, texture_coords)
+    }
+
+    // Consecutive curves that don't meet get joined by a straight segment
+    fn stitch(pieces: Vec<(Curve, Point, Point)>) -> Contour {
+        let n = pieces.len();
+        let mut curves = vec![];
+        let mut texture_coords = vec![];
+
+        for (index, (curve, texture_start, texture_end)) in pieces.iter().enumerate() {
+            let next = pieces[(index + 1) % n].0;
+
+            curves.push(*curve);
+            texture_coords.push(*texture_start);
+
+            if (curve.end() - next.start()).length() > EPS {
+                curves.push(Curve::Segment(LineSegment::new(curve.end(), next.start())));
+                texture_coords.push(*texture_end);
+            }
+        }
+
+        Contour::new(curves, texture_coords)
+    }
+
+    fn pieces(&self) -> Vec<(Curve, Point, Point)> {
+        (0..self.num_curves())
+            .map(|index| {
+                (
+                    self.curves[index],
+                    self.texture_coord_at(index, 0.0),
+                    self.texture_coord_at(index, 1.0),
+                )
+            })
+            .collect()
+    }
+}
+
+impl From<&Polygon> for Contour {
+    fn from(polygon: &Polygon) -> Self {
+        Contour::new(
+            polygon.border().into_iter().map(Curve::Segment).collect(),
+            polygon.texture_coords().clone(),
+        )
+    }
+}
+
+impl From<Polygon> for Contour {
+    fn from(polygon: Polygon) -> Self {
+        Contour::from(&polygon)
+    }
+}
+
+impl Reflectable for Contour {
+    type InvertOutType = Option<Contour>;
+
+    fn reflect_over_point(&self, origin: Point) -> Self {
+        Contour {
+            curves: self
+                .curves
+                .iter()
+                .map(|curve| curve.reflect_over_point(origin))
+                .collect(),
+            texture_coords: self.texture_coords.clone(),
+        }
+    }
+
+    fn reflect_over_line(&self, line: Line) -> Self {
+        Contour {
+            curves: self
+                .curves
+                .iter()
+                .map(|curve| curve.reflect_over_line(line))
+                .collect(),
+            texture_coords: self.texture_coords.clone(),
+        }
+        .reversed()
+    }
+
+    fn invert_over_circle(&self, circle: Circle) -> Self::InvertOutType {
+        let pieces: Vec<_> = self
+            .pieces()
+            .into_iter()
+            .filter_map(|(curve, texture_start, texture_end)| {
+                Some((
+                    curve.invert_over_circle(circle)?,
+                    texture_start,
+                    texture_end,
+                ))
+            })
+            .collect();
+
+        if pieces.is_empty() {
+            return None;
+        }
+
+        Contour::stitch(pieces).sanitize(0.001, 0.001)
+    }
+}
+
+impl Croppable for Contour {
+    fn crop_to_halfplane(&self, line: Line, side: f32) -> Option<Self> {
+        let mut pieces = vec![];
+
+        for (curve, texture_start, texture_end) in self.pieces() {
+            let cuts: Vec<f32> = [0.0]
+                .into_iter()
+                .chain(curve.intersect_line(line))
+                .chain([1.0])
+                .collect();
+
+            for window in cuts.windows(2) {
+                let piece = curve.sub_curve(window[0], window[1]);
+
+                if line.is_on_side(piece.mid_point(), side) {
+                    pieces.push((
+                        piece,
+                        texture_start.lerp(texture_end, window[0]),
+                        texture_start.lerp(texture_end, window[1]),
+                    ));
+                }
+            }
+        }
+
+        if pieces.is_empty() {
+            return None;
+        }
+
+        Contour::stitch(pieces).sanitize(EPS, 0.01)
+    }
+}
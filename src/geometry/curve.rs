@@ -0,0 +1,139 @@
+use super::utils::{cross, EPS};
+use super::{Arc, Circle, Line, LineSegment, Point, Reflectable};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Curve {
+    Segment(LineSegment),
+    Arc(Arc),
+}
+
+impl Curve {
+    pub fn point_at(&self, t: f32) -> Point {
+        match self {
+            Curve::Segment(seg) => seg.interpolate_position(t, 1.0 - t),
+            Curve::Arc(arc) => arc.point_at(t),
+        }
+    }
+
+    pub fn start(&self) -> Point {
+        self.point_at(0.0)
+    }
+
+    pub fn end(&self) -> Point {
+        self.point_at(1.0)
+    }
+
+    pub fn mid_point(&self) -> Point {
+        self.point_at(0.5)
+    }
+
+    pub fn length(&self) -> f32 {
+        match self {
+            Curve::Segment(seg) => seg.length(),
+            Curve::Arc(arc) => arc.length(),
+        }
+    }
+
+    pub fn reverse(&self) -> Curve {
+        match self {
+            Curve::Segment(seg) => {
+                let (a, b) = seg.endpoints();
+                Curve::Segment(LineSegment::new(b, a))
+            }
+            Curve::Arc(arc) => Curve::Arc(arc.reverse()),
+        }
+    }
+
+    pub fn sub_curve(&self, t0: f32, t1: f32) -> Curve {
+        match self {
+            Curve::Segment(_) => {
+                Curve::Segment(LineSegment::new(self.point_at(t0), self.point_at(t1)))
+            }
+            Curve::Arc(arc) => Curve::Arc(arc.sub_arc(t0, t1)),
+        }
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        match self {
+            Curve::Segment(seg) => seg.contains(p),
+            Curve::Arc(arc) => arc.contains(p),
+        }
+    }
+
+    // Parameters in (0, 1) where the curve crosses the line, in increasing order
+    pub fn intersect_line(&self, line: Line) -> Vec<f32> {
+        match self {
+            Curve::Segment(seg) => {
+                let (a, b) = seg.endpoints();
+                let (side_a, side_b) = (line.side(a), line.side(b));
+
+                if (side_a - side_b).abs() < EPS {
+                    return vec![];
+                }
+
+                let t = side_a / (side_a - side_b);
+                if EPS < t && t < 1.0 - EPS {
+                    vec![t]
+                } else {
+                    vec![]
+                }
+            }
+            Curve::Arc(arc) => arc.intersect_line(line),
+        }
+    }
+
+    pub fn subdivisions(&self, tolerance: f32) -> usize {
+        match self {
+            Curve::Segment(_) => 1,
+            Curve::Arc(arc) => arc.subdivisions(tolerance),
+        }
+    }
+
+    pub fn area_term(&self) -> f32 {
+        match self {
+            Curve::Segment(seg) => {
+                let (a, b) = seg.endpoints();
+                cross(a, b)
+            }
+            Curve::Arc(arc) => arc.area_term(),
+        }
+    }
+
+    // Only exact for maps that send circles to circles, which is all we ever apply
+    fn map_points(&self, f: impl Fn(Point) -> Option<Point>) -> Option<Curve> {
+        let start = f(self.start())?;
+        let end = f(self.end())?;
+
+        Some(match self {
+            Curve::Segment(_) => Curve::Segment(LineSegment::new(start, end)),
+            Curve::Arc(_) => Arc::through(start, f(self.mid_point())?, end),
+        })
+    }
+}
+
+impl Reflectable for Curve {
+    type InvertOutType = Option<Curve>;
+
+    fn reflect_over_point(&self, origin: Point) -> Self {
+        self.map_points(|p| Some(p.reflect_over_point(origin)))
+            .unwrap()
+    }
+
+    fn reflect_over_line(&self, line: Line) -> Self {
+        self.map_points(|p| Some(p.reflect_over_line(line)))
+            .unwrap()
+    }
+
+    fn invert_over_circle(&self, circle: Circle) -> Self::InvertOutType {
+        // A curve through the center is sent off to infinity
+        if self.contains(circle.center()) {
+            return None;
+        }
+
+        let start = self.start().invert_over_circle(circle)?;
+        let end = self.end().invert_over_circle(circle)?;
+        let mid = self.mid_point().invert_over_circle(circle)?;
+
+        Some(Arc::through(start, mid, end))
+    }
+}
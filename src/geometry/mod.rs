@@ -1,15 +1,22 @@
 pub use bevy::math::Vec2 as Point;
 
+mod arc;
+mod boolean;
 mod circle;
+mod contour;
 mod croppable;
+mod curve;
 mod point;
 mod polygon;
 mod reflectable;
 mod segments;
 pub mod utils;
 
+pub use arc::Arc;
 pub use circle::Circle;
+pub use contour::Contour;
 pub use croppable::Croppable;
+pub use curve::Curve;
 pub use polygon::Polygon;
 pub use reflectable::Reflectable;
 pub use segments::{Line, LineSegment};
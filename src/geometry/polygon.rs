@@ -1,7 +1,5 @@
-use std::cmp;
-
-use super::utils::{num_integrate, signed_area, split_weight_function, EPS};
-use super::{Circle, Point};
+use super::utils::{cross, in_triangle, signed_area, EPS};
+use super::{Circle, Contour, Point};
 use super::{Croppable, Line, LineSegment, Reflectable};
 use bevy::{
     prelude::*,
@@ -44,6 +42,13 @@ impl Polygon {
         signed_area(&self.vertices)
     }
 
+    pub fn bounding_box(&self) -> (Point, Point) {
+        self.vertices.iter().fold(
+            (Point::splat(f32::INFINITY), Point::splat(f32::NEG_INFINITY)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        )
+    }
+
     pub fn sanitize_polynomial(&self, min_dist: f32, min_area: f32) -> Option<Polygon> {
         if self.num_vertices() < 3 {
             return None;
@@ -75,6 +80,149 @@ impl Polygon {
         }
     }
 
+    pub fn triangulate(&self) -> Vec<[usize; 3]> {
+        let mut remaining: Vec<usize> = (0..self.num_vertices()).collect();
+        let mut triangles = vec![];
+
+        while remaining.len() > 3 {
+            let n = remaining.len();
+            let corner = |i: usize| {
+                (
+                    self.vertices[remaining[(i + n - 1) % n]],
+                    self.vertices[remaining[i]],
+                    self.vertices[remaining[(i + 1) % n]],
+                )
+            };
+
+            if let Some(i) = (0..n).find(|i| self.is_ear(&remaining, *i)) {
+                triangles.push([
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                ]);
+                remaining.remove(i);
+            } else if let Some(i) = (0..n).find(|i| {
+                let (a, b, c) = corner(*i);
+                cross(b - a, c - b).abs() < EPS
+            }) {
+                // Collinear leftovers only contribute zero area triangles
+                remaining.remove(i);
+            } else {
+                // Numerically broken input, clip the most convex corner so we still terminate
+                let i = (0..n)
+                    .max_by(|i, j| {
+                        let (a, b, c) = corner(*i);
+                        let (d, e, f) = corner(*j);
+                        cross(b - a, c - b).total_cmp(&cross(e - d, f - e))
+                    })
+                    .unwrap();
+                triangles.push([
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                ]);
+                remaining.remove(i);
+            }
+        }
+
+        if remaining.len() == 3 {
+            triangles.push([remaining[0], remaining[1], remaining[2]]);
+        }
+
+        triangles
+    }
+
+    fn is_ear(&self, remaining: &[usize], i: usize) -> bool {
+        let n = remaining.len();
+        let (prev, curr, next) = (
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        );
+        let (a, b, c) = (
+            self.vertices[prev],
+            self.vertices[curr],
+            self.vertices[next],
+        );
+
+        if cross(b - a, c - b) <= EPS {
+            return false;
+        }
+
+        !remaining.iter().any(|j| {
+            let p = self.vertices[*j];
+            *j != prev
+                && *j != curr
+                && *j != next
+                && p != a
+                && p != b
+                && p != c
+                && in_triangle(p, a, b, c)
+        })
+    }
+
+    pub fn convex_decomposition(&self) -> Vec<Polygon> {
+        let mut pieces: Vec<Vec<usize>> = self
+            .triangulate()
+            .into_iter()
+            .map(|triangle| triangle.to_vec())
+            .collect();
+
+        // Hertel-Mehlhorn: drop triangulation diagonals as long as both sides stay convex
+        let mut merged_any = true;
+        while merged_any {
+            merged_any = false;
+
+            'search: for a in 0..pieces.len() {
+                for b in a + 1..pieces.len() {
+                    if let Some(merged) = self.merge_convex(&pieces[a], &pieces[b]) {
+                        pieces[a] = merged;
+                        pieces.swap_remove(b);
+                        merged_any = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        pieces
+            .into_iter()
+            .map(|piece| Polygon {
+                vertices: piece.iter().map(|i| self.vertices[*i]).collect(),
+                texture_coords: piece.iter().map(|i| self.texture_coords[*i]).collect(),
+            })
+            .collect()
+    }
+
+    fn merge_convex(&self, a: &[usize], b: &[usize]) -> Option<Vec<usize>> {
+        let (n, m) = (a.len(), b.len());
+
+        for i in 0..n {
+            let (u, v) = (a[i], a[(i + 1) % n]);
+            if let Some(j) = (0..m).find(|j| b[*j] == v && b[(j + 1) % m] == u) {
+                let merged: Vec<usize> = (1..=n)
+                    .map(|k| a[(i + k) % n])
+                    .chain((2..m).map(|k| b[(j + k) % m]))
+                    .collect();
+
+                return self.is_convex(&merged).then_some(merged);
+            }
+        }
+
+        None
+    }
+
+    fn is_convex(&self, piece: &[usize]) -> bool {
+        let n = piece.len();
+
+        (0..n).all(|i| {
+            let a = self.vertices[piece[i]];
+            let b = self.vertices[piece[(i + 1) % n]];
+            let c = self.vertices[piece[(i + 2) % n]];
+            cross(b - a, c - b) >= -EPS
+        })
+    }
+
     pub fn border(&self) -> Vec<LineSegment> {
         let n = self.num_vertices();
 
@@ -84,10 +232,8 @@ impl Polygon {
     }
 }
 
-const POLYGON_RES: usize = 120;
-
 impl Reflectable for Polygon {
-    type InvertOutType = Option<Polygon>;
+    type InvertOutType = Option<Contour>;
 
     fn reflect_over_line(&self, line: super::Line) -> Self {
         Polygon {
@@ -114,49 +260,7 @@ impl Reflectable for Polygon {
     }
 
     fn invert_over_circle(&self, circle: Circle) -> Self::InvertOutType {
-        let segments = self.border();
-
-        let n = self.num_vertices();
-        let texture_coords_segs =
-            (0..n).map(|j| (self.texture_coords[j], self.texture_coords[(j + 1) % n]));
-
-        let integral_cnt: Vec<usize> = segments
-            .iter()
-            .map(|seg| cmp::min(400, (seg.length() / 0.05).ceil() as usize))
-            .collect();
-
-        let sizes: Vec<f32> = segments
-            .iter()
-            .zip(integral_cnt.iter())
-            .map(|(seg, n)| {
-                *num_integrate(*seg, *n, &|x| split_weight_function(x, circle.center()))
-                    .last()
-                    .unwrap()
-            })
-            .collect();
-        let total: f32 = sizes.iter().sum();
-
-        let mut verts_out = Vec::<Point>::new();
-        let mut texture_coords_out = Vec::<Point>::new();
-
-        for ((seg, size), (t_a, t_b)) in segments.iter().zip(sizes.iter()).zip(texture_coords_segs)
-        {
-            let split = seg.weighted_split(
-                &|x| split_weight_function(x, circle.center()),
-                (size * (POLYGON_RES as f32) / total).ceil() as usize,
-            );
-
-            let (a, b) = seg.endpoints();
-
-            for vertex in split {
-                if let Some(p) = vertex.invert_over_circle(circle) {
-                    verts_out.push(p);
-                    texture_coords_out.push(interpolate_texture_coords(a, t_a, b, t_b, vertex));
-                }
-            }
-        }
-
-        Polygon::new(verts_out, texture_coords_out).sanitize_polynomial(0.001, 0.001)
+        Contour::from(self).invert_over_circle(circle)
     }
 }
 
@@ -257,8 +361,11 @@ impl From<&Polygon> for Mesh {
                     .collect::<Vec<_>>(),
             )
             .with_indices(Some(Indices::U32(
-                (1..(polygon.num_vertices() as u32 - 1))
-                    .flat_map(|i| [0, i, i + 1])
+                polygon
+                    .triangulate()
+                    .into_iter()
+                    .flatten()
+                    .map(|i| i as u32)
                     .collect::<Vec<_>>(),
             )))
     }
@@ -266,6 +373,18 @@ impl From<&Polygon> for Mesh {
 
 impl From<Polygon> for Collider {
     fn from(polygon: Polygon) -> Self {
+        let pieces: Vec<_> = polygon
+            .convex_decomposition()
+            .into_iter()
+            .filter_map(|piece| Collider::convex_polyline(piece.vertices))
+            .map(|collider| (Vec2::ZERO, 0.0, collider))
+            .collect();
+
+        if !pieces.is_empty() {
+            return Collider::compound(pieces);
+        }
+
+        // Too thin to have a convex hull, keep the outline so it still collides
         let n = polygon.num_vertices();
         Collider::polyline(
             polygon.vertices,
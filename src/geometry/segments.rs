@@ -1,7 +1,5 @@
-use std::cmp;
-
-use super::utils::{cross, num_integrate, split_weight_function, EPS};
-use super::{Circle, Croppable, Point, Reflectable};
+use super::utils::{cross, EPS};
+use super::{Circle, Croppable, Curve, Point, Reflectable};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Line {
@@ -160,43 +158,10 @@ impl LineSegment {
     pub fn interpolate_position(&self, w1: f32, w2: f32) -> Point {
         (w2 * self.start + w1 * self.end) / (w1 + w2)
     }
-
-    pub fn weighted_split(self, f: &dyn Fn(Point) -> f32, num_splits: usize) -> Vec<Point> {
-        let n = cmp::min(200, (self.length() / 0.05).ceil() as usize);
-
-        let intermediate = num_integrate(self, n, f);
-
-        let m = num_splits + 1;
-
-        let total = intermediate.last().unwrap();
-        let step_sizes = total / (m as f32);
-
-        let mut p1: usize = 0;
-
-        let mut res = vec![];
-
-        for p2 in 0..n {
-            let a = self.interpolate_position(p2 as f32, (n - p2) as f32);
-            let b = self.interpolate_position((p2 + 1) as f32, (n - p2 - 1) as f32);
-            let seg = LineSegment::new(a, b);
-            while p1 <= m && step_sizes * (p1 as f32) <= EPS * total + intermediate[p2 + 1] {
-                let curr_value = step_sizes * (p1 as f32);
-                res.push(seg.interpolate_position(
-                    curr_value - intermediate[p2],
-                    intermediate[p2 + 1] - curr_value,
-                ));
-                p1 += 1;
-            }
-        }
-
-        res
-    }
 }
 
-const LINESEG_RES: usize = 30;
-
 impl Reflectable for LineSegment {
-    type InvertOutType = Vec<LineSegment>;
+    type InvertOutType = Option<Curve>;
 
     fn reflect_over_line(&self, line: Line) -> Self {
         LineSegment::new(
@@ -213,13 +178,7 @@ impl Reflectable for LineSegment {
     }
 
     fn invert_over_circle(&self, circle: Circle) -> Self::InvertOutType {
-        let points =
-            self.weighted_split(&|x| split_weight_function(x, circle.center()), LINESEG_RES);
-
-        points
-            .windows(2)
-            .map(|segs| LineSegment::new(segs[0], segs[1]))
-            .collect()
+        Curve::Segment(*self).invert_over_circle(circle)
     }
 }
 
@@ -16,10 +16,12 @@ pub enum AppState {
     GameOver,
 }
 
-use game::{GamePlugin, KeyBindings, PlayerControls};
+use game::GamePlugin;
 use menu::MenuPlugin;
+use settings::SettingsPlugin;
 
 mod geometry;
+mod settings;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub fn run() {
@@ -42,29 +44,15 @@ pub fn run() {
                 level: bevy::log::Level::DEBUG,
             })
             .set(ImagePlugin::default_nearest()),
-    )
-    .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
-    .insert_resource(PlayerControls {
-        controls: vec![
-            KeyBindings {
-                left: KeyCode::A,
-                right: KeyCode::D,
-                jump: KeyCode::W,
-                shoot: KeyCode::C,
-                powerup: KeyCode::V,
-                butterfly: KeyCode::B,
-            },
-            KeyBindings {
-                left: KeyCode::Left,
-                right: KeyCode::Right,
-                jump: KeyCode::Up,
-                shoot: KeyCode::Comma,
-                powerup: KeyCode::Period,
-                butterfly: KeyCode::Slash,
-            },
-        ],
-    })
-    .add_plugins((GamePlugin, MenuPlugin))
-    .add_state::<AppState>()
-    .run();
+    );
+
+    // Loaded after the log plugin is set up, so problems with the saved settings get reported
+    let saved = settings::load();
+
+    app.insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
+        .insert_resource(saved.controls)
+        .insert_resource(saved.match_settings)
+        .add_plugins((GamePlugin, MenuPlugin, SettingsPlugin))
+        .add_state::<AppState>()
+        .run();
 }
@@ -2,7 +2,10 @@ use bevy::prelude::*;
 use bevy::ui::ContentSize;
 use bevy::{app::Plugin, ecs::schedule::OnEnter};
 
-use crate::game::{PlayerAction, PlayerControls};
+use crate::game::{
+    AvailableCharacters, AvailableMaps, BotDifficulty, KeyBindings, MapDescription, MatchSettings,
+    PlayerAction, PlayerControls, SelectedMap, MAX_PLAYERS,
+};
 use crate::AppState;
 
 pub struct MenuPlugin;
@@ -16,18 +19,30 @@ impl Plugin for MenuPlugin {
                     menu_action.run_if(in_state(AppState::MainMenu)),
                     select_key_binding.run_if(in_state(AppState::MainMenu)),
                     set_key_binding.run_if(in_state(AppState::MainMenu)),
+                    resolve_binding_conflict.run_if(in_state(AppState::MainMenu)),
+                    update_binding_labels.run_if(in_state(AppState::MainMenu)),
+                    populate_map_picker.run_if(in_state(AppState::MainMenu)),
+                    highlight_selected_map.run_if(in_state(AppState::MainMenu)),
+                    update_setting_text.run_if(in_state(AppState::MainMenu)),
+                    update_character_text.run_if(in_state(AppState::MainMenu)),
                 ),
             )
             .add_systems(OnExit(AppState::MainMenu), cleanup)
             .add_event::<SelectKeyBinding>()
-            .add_event::<SetKeyBinding>();
+            .add_event::<SetKeyBinding>()
+            .add_event::<ResolveBindingConflict>();
     }
 }
 
 #[derive(Component)]
 enum MenuButtonAction {
     Play,
+    SelectMap { index: usize },
+    ChangeSetting { setting: MatchSetting, delta: i32 },
+    ChangeCharacter { player: usize, delta: i32 },
     SelectKeyBinding { player: u32, action: PlayerAction },
+    RestoreDefaultBindings { player: usize },
+    ResolveBindingConflict { swap: bool },
 }
 
 #[derive(Component, Debug)]
@@ -36,7 +51,14 @@ enum MenuState {
     SelectKeyBinding {
         player: u32,
         action: PlayerAction,
-        text_ref: Entity,
+    },
+    // The new input is already bound elsewhere, waiting for the player to swap or cancel
+    ResolveBindingConflict {
+        player: u32,
+        action: PlayerAction,
+        input: BindingInput,
+        other_player: usize,
+        other_action: PlayerAction,
     },
 }
 
@@ -48,13 +70,120 @@ pub struct SelectKeyBinding {
 
 #[derive(Event)]
 pub struct SetKeyBinding {
-    pub key: KeyCode,
+    pub input: BindingInput,
+}
+
+#[derive(Event)]
+pub struct ResolveBindingConflict {
+    pub swap: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum BindingInput {
+    Key(KeyCode),
+    GamepadButton(GamepadButton),
 }
 
 #[derive(Component)]
 struct MainMenu;
 
-fn main_menu_setup(mut commands: Commands, controls: Res<PlayerControls>) {
+// Text of a binding button, kept in sync with PlayerControls
+#[derive(Component)]
+struct BindingLabel {
+    player: usize,
+    action: PlayerAction,
+}
+
+// Asks whether to swap bindings when the chosen input is already taken
+#[derive(Component)]
+struct BindingConflictPrompt;
+
+// Filled with one button per map once they have all loaded
+#[derive(Component)]
+struct MapPicker;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MatchSetting {
+    Players,
+    Bots,
+    BotDifficulty,
+    Stock,
+    Teams,
+    TeamDamage,
+    SelfHit,
+}
+
+impl MatchSetting {
+    fn label(self) -> &'static str {
+        match self {
+            MatchSetting::Players => "Players",
+            MatchSetting::Bots => "Bots",
+            MatchSetting::BotDifficulty => "Bot difficulty",
+            MatchSetting::Stock => "Lives",
+            MatchSetting::Teams => "Teams",
+            MatchSetting::TeamDamage => "Team damage",
+            MatchSetting::SelfHit => "Hit by own bullets",
+        }
+    }
+
+    fn text(self, settings: &MatchSettings) -> String {
+        match self {
+            MatchSetting::Players => settings.player_count.to_string(),
+            MatchSetting::Bots => settings.bots.to_string(),
+            MatchSetting::BotDifficulty => format!("{:?}", settings.bot_difficulty),
+            MatchSetting::Stock => settings.stock.to_string(),
+            MatchSetting::Teams => on_off(settings.teams),
+            MatchSetting::TeamDamage => on_off(settings.hit_rules.team_damage),
+            MatchSetting::SelfHit => on_off(settings.hit_rules.self_hit),
+        }
+    }
+
+    fn change(self, settings: &mut MatchSettings, delta: i32) {
+        let step = |value: usize| value.saturating_add_signed(delta as isize);
+        match self {
+            MatchSetting::Players => settings.player_count = step(settings.player_count),
+            MatchSetting::Bots => settings.bots = step(settings.bots),
+            MatchSetting::BotDifficulty => {
+                const DIFFICULTIES: [BotDifficulty; 3] = [
+                    BotDifficulty::Easy,
+                    BotDifficulty::Normal,
+                    BotDifficulty::Hard,
+                ];
+                let index = DIFFICULTIES
+                    .iter()
+                    .position(|difficulty| *difficulty == settings.bot_difficulty)
+                    .unwrap_or(0);
+                settings.bot_difficulty = DIFFICULTIES[step(index).min(DIFFICULTIES.len() - 1)];
+            }
+            MatchSetting::Stock => settings.stock = step(settings.stock as usize) as u32,
+            // Minus turns these off and plus turns them on
+            MatchSetting::Teams => settings.teams = delta > 0,
+            MatchSetting::TeamDamage => settings.hit_rules.team_damage = delta > 0,
+            MatchSetting::SelfHit => settings.hit_rules.self_hit = delta > 0,
+        }
+        settings.clamp();
+    }
+}
+
+fn on_off(value: bool) -> String {
+    if value { "On" } else { "Off" }.to_string()
+}
+
+#[derive(Component)]
+struct SettingText(MatchSetting);
+
+// Name of the character a player slot has picked
+#[derive(Component)]
+struct CharacterText(usize);
+
+const MAP_PREVIEW_WIDTH: f32 = 160.0;
+const MAP_PREVIEW_HEIGHT: f32 = 90.0;
+
+fn main_menu_setup(
+    mut commands: Commands,
+    controls: Res<PlayerControls>,
+    settings: Res<MatchSettings>,
+) {
     commands.spawn((MenuState::Default, MainMenu));
     commands
         .spawn((
@@ -105,6 +234,56 @@ fn main_menu_setup(mut commands: Commands, controls: Res<PlayerControls>) {
                         .with_children(|parrent| {
                             parrent.spawn(TextBundle::from_section("Play", TextStyle::default()));
                         });
+                    parrent.spawn(TextBundle::from_section(
+                        "Map",
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    ));
+                    parrent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                margin: UiRect::bottom(Val::Px(20.0)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        MapPicker,
+                    ));
+                    for setting in [
+                        MatchSetting::Players,
+                        MatchSetting::Bots,
+                        MatchSetting::BotDifficulty,
+                        MatchSetting::Stock,
+                        MatchSetting::Teams,
+                        MatchSetting::TeamDamage,
+                        MatchSetting::SelfHit,
+                    ] {
+                        spawn_setting_counter(parrent, setting, &settings);
+                    }
+                    parrent.spawn(TextBundle::from_section(
+                        "Characters",
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    ));
+                    parrent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                column_gap: Val::Px(20.0),
+                                margin: UiRect::bottom(Val::Px(20.0)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parrent| {
+                            for player in 0..MAX_PLAYERS {
+                                spawn_character_picker(parrent, player);
+                            }
+                        });
                     parrent.spawn(TextBundle::from_section(
                         "Controls",
                         TextStyle {
@@ -113,9 +292,16 @@ fn main_menu_setup(mut commands: Commands, controls: Res<PlayerControls>) {
                         },
                     ));
                     parrent
-                        .spawn(NodeBundle::default())
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_wrap: FlexWrap::Wrap,
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
                         .with_children(|parrent| {
-                            for i in 0..2 {
+                            for i in 0..controls.controls.len() {
                                 parrent
                                     .spawn(NodeBundle {
                                         style: Style {
@@ -163,7 +349,13 @@ fn main_menu_setup(mut commands: Commands, controls: Res<PlayerControls>) {
                                                 for (label, action, note) in [
                                                     ("Left", PlayerAction::Left, ""),
                                                     ("Right", PlayerAction::Right, ""),
+                                                    (
+                                                        "Down",
+                                                        PlayerAction::Down,
+                                                        "(with jump to drop through)",
+                                                    ),
                                                     ("Jump", PlayerAction::Jump, ""),
+                                                    ("Dash", PlayerAction::Dash, ""),
                                                     ("Shoot", PlayerAction::Shoot, ""),
                                                     (
                                                         "Use Powerup",
@@ -217,15 +409,19 @@ fn main_menu_setup(mut commands: Commands, controls: Res<PlayerControls>) {
                                                                     ..default()
                                                                 }, MenuButtonAction::SelectKeyBinding { player: i as u32, action }))
                                                                 .with_children(|parrent| {
-                                                                    parrent.spawn(
+                                                                    parrent.spawn((
                                                                         TextBundle::from_section(
-                                                                            format!(
-                                                                                "{:?}",
-                                                                                controls.controls[i][action]
+                                                                            binding_label(
+                                                                                &controls.controls[i],
+                                                                                action,
                                                                             ),
                                                                             TextStyle::default(),
                                                                         ),
-                                                                    );
+                                                                        BindingLabel {
+                                                                            player: i,
+                                                                            action,
+                                                                        },
+                                                                    ));
                                                                 });
                                                         });
                                                     parrent.spawn(TextBundle::from_section(
@@ -234,6 +430,7 @@ fn main_menu_setup(mut commands: Commands, controls: Res<PlayerControls>) {
                                                     ));
                                                 }
                                             });
+                                        spawn_restore_defaults_button(parrent, i);
                                     });
                             }
                         });
@@ -242,7 +439,9 @@ fn main_menu_setup(mut commands: Commands, controls: Res<PlayerControls>) {
 }
 
 fn menu_action(
+    mut commands: Commands,
     keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
     interaction_query: Query<
         (&Interaction, &MenuButtonAction),
         (Changed<Interaction>, With<Button>),
@@ -251,28 +450,312 @@ fn menu_action(
     mut select_key_binding_send: EventWriter<SelectKeyBinding>,
     mut set_key_binding_send: EventWriter<SetKeyBinding>,
     menu_state: Query<&MenuState>,
+    maps: AvailableMaps,
+    characters: AvailableCharacters,
+    mut selected_map: ResMut<SelectedMap>,
+    mut settings: ResMut<MatchSettings>,
+    mut controls: ResMut<PlayerControls>,
+    mut resolve_conflict_send: EventWriter<ResolveBindingConflict>,
 ) {
     let menu_state = menu_state.iter().next().expect("Menu state should exist");
+    let mut play = false;
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
             match *menu_button_action {
-                MenuButtonAction::Play => app_state.set(AppState::InGame),
+                MenuButtonAction::Play => play = true,
+                MenuButtonAction::SelectMap { index } => selected_map.0 = index,
+                MenuButtonAction::ChangeSetting { setting, delta } => {
+                    setting.change(&mut settings, delta)
+                }
+                MenuButtonAction::ChangeCharacter { player, delta } => {
+                    if let Some(count) = characters.all().map(|all| all.len() as i32) {
+                        let index = settings.characters[player] as i32 + delta;
+                        settings.characters[player] = index.rem_euclid(count.max(1)) as usize;
+                    }
+                }
                 MenuButtonAction::SelectKeyBinding { player, action } => {
                     select_key_binding_send.send(SelectKeyBinding { player, action })
                 }
+                MenuButtonAction::RestoreDefaultBindings { player } => {
+                    controls.controls[player] = PlayerControls::default().controls[player];
+                }
+                MenuButtonAction::ResolveBindingConflict { swap } => {
+                    resolve_conflict_send.send(ResolveBindingConflict { swap })
+                }
             }
         }
     }
     if let Some(key) = keyboard_input.get_just_pressed().next().copied() {
         println!("{:?}", menu_state);
-        if let MenuState::SelectKeyBinding { .. } = menu_state {
-            set_key_binding_send.send(SetKeyBinding { key });
-        } else {
-            app_state.set(AppState::InGame);
+        match menu_state {
+            MenuState::Default => play = true,
+            MenuState::SelectKeyBinding { .. } => set_key_binding_send.send(SetKeyBinding {
+                input: BindingInput::Key(key),
+            }),
+            // Only the prompt buttons answer a conflict
+            MenuState::ResolveBindingConflict { .. } => {}
+        }
+    }
+    if let Some(button) = gamepad_buttons.get_just_pressed().next().copied() {
+        match menu_state {
+            MenuState::Default => play |= button.button_type == GamepadButtonType::Start,
+            MenuState::SelectKeyBinding { .. } => set_key_binding_send.send(SetKeyBinding {
+                input: BindingInput::GamepadButton(button),
+            }),
+            MenuState::ResolveBindingConflict { .. } => {}
+        }
+    }
+    // The map and character files are loaded in the background, don't start before they are there
+    let ready = characters.all().is_some_and(|all| !all.is_empty());
+    if let Some(map) = maps.get(selected_map.0).filter(|_| play && ready) {
+        commands.insert_resource(map.clone());
+        app_state.set(AppState::InGame);
+    }
+}
+
+fn populate_map_picker(
+    mut commands: Commands,
+    pickers: Query<Entity, (With<MapPicker>, Without<Children>)>,
+    available_maps: AvailableMaps,
+) {
+    let Some(maps) = available_maps.all() else {
+        return;
+    };
+    for picker in &pickers {
+        for path in available_maps.failed() {
+            warn!("Map {} failed to load and is left out", path);
+        }
+        commands.entity(picker).with_children(|parrent| {
+            for (index, map) in maps.iter().enumerate() {
+                parrent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                margin: UiRect::all(Val::Px(5.0)),
+                                padding: UiRect::all(Val::Px(5.0)),
+                                ..default()
+                            },
+                            background_color: Color::GRAY.into(),
+                            ..default()
+                        },
+                        MenuButtonAction::SelectMap { index },
+                    ))
+                    .with_children(|parrent| {
+                        spawn_map_preview(parrent, map);
+                        parrent.spawn(TextBundle::from_section(
+                            map.name.clone(),
+                            TextStyle::default(),
+                        ));
+                    });
+            }
+        });
+    }
+}
+
+// Draws the platforms as rectangles, scaled to fit the preview without stretching
+fn spawn_map_preview(parrent: &mut ChildBuilder, map: &MapDescription) {
+    let bounds = map
+        .platforms
+        .iter()
+        .map(|platform| platform.bounds())
+        .reduce(|a, b| a.union(b))
+        .unwrap_or_default();
+    let scale = (MAP_PREVIEW_WIDTH / bounds.width()).min(MAP_PREVIEW_HEIGHT / bounds.height());
+    let offset = (Vec2::new(MAP_PREVIEW_WIDTH, MAP_PREVIEW_HEIGHT) - bounds.size() * scale) / 2.0;
+
+    parrent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(MAP_PREVIEW_WIDTH),
+                height: Val::Px(MAP_PREVIEW_HEIGHT),
+                margin: UiRect::bottom(Val::Px(5.0)),
+                ..default()
+            },
+            background_color: Color::BLACK.into(),
+            ..default()
+        })
+        .with_children(|parrent| {
+            for platform in &map.platforms {
+                let rect = platform.bounds();
+                parrent.spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        // UI coordinates grow downwards
+                        left: Val::Px(offset.x + (rect.min.x - bounds.min.x) * scale),
+                        top: Val::Px(offset.y + (bounds.max.y - rect.max.y) * scale),
+                        width: Val::Px(rect.width() * scale),
+                        height: Val::Px(rect.height() * scale),
+                        ..default()
+                    },
+                    background_color: if platform.one_way {
+                        Color::rgb(0.2, 0.4, 0.15).into()
+                    } else {
+                        Color::rgb(0.3, 0.6, 0.2).into()
+                    },
+                    ..default()
+                });
+            }
+        });
+}
+
+fn highlight_selected_map(
+    selected_map: Res<SelectedMap>,
+    mut buttons: Query<(&MenuButtonAction, &mut BackgroundColor, Ref<Button>)>,
+) {
+    for (action, mut background_color, button) in &mut buttons {
+        if let MenuButtonAction::SelectMap { index } = *action {
+            if selected_map.is_changed() || button.is_added() {
+                background_color.0 = if index == selected_map.0 {
+                    Color::BLUE
+                } else {
+                    Color::GRAY
+                };
+            }
         }
     }
 }
 
+fn spawn_setting_counter(
+    parrent: &mut ChildBuilder,
+    setting: MatchSetting,
+    settings: &MatchSettings,
+) {
+    parrent
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(10.0),
+                margin: UiRect::bottom(Val::Px(10.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parrent| {
+            parrent.spawn(TextBundle::from_section(
+                setting.label(),
+                TextStyle {
+                    font_size: 20.0,
+                    ..default()
+                },
+            ));
+            spawn_setting_button(parrent, setting, "-", -1);
+            parrent.spawn((
+                TextBundle::from_section(setting.text(settings), TextStyle::default()),
+                SettingText(setting),
+            ));
+            spawn_setting_button(parrent, setting, "+", 1);
+        });
+}
+
+fn spawn_setting_button(
+    parrent: &mut ChildBuilder,
+    setting: MatchSetting,
+    label: &str,
+    delta: i32,
+) {
+    parrent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(30.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::GRAY.into(),
+                ..default()
+            },
+            MenuButtonAction::ChangeSetting { setting, delta },
+        ))
+        .with_children(|parrent| {
+            parrent.spawn(TextBundle::from_section(label, TextStyle::default()));
+        });
+}
+
+fn update_setting_text(settings: Res<MatchSettings>, mut texts: Query<(&mut Text, &SettingText)>) {
+    if settings.is_changed() {
+        for (mut text, SettingText(setting)) in &mut texts {
+            text.sections[0].value = setting.text(&settings);
+        }
+    }
+}
+
+fn spawn_character_picker(parrent: &mut ChildBuilder, player: usize) {
+    parrent
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(5.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parrent| {
+            parrent.spawn(TextBundle::from_section(
+                format!("P{}", player + 1),
+                TextStyle {
+                    font_size: 20.0,
+                    ..default()
+                },
+            ));
+            for (label, delta) in [("<", -1), (">", 1)] {
+                parrent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(30.0),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: Color::GRAY.into(),
+                            ..default()
+                        },
+                        MenuButtonAction::ChangeCharacter { player, delta },
+                    ))
+                    .with_children(|parrent| {
+                        parrent.spawn(TextBundle::from_section(label, TextStyle::default()));
+                    });
+                if delta < 0 {
+                    parrent.spawn((
+                        TextBundle::from_section("...", TextStyle::default()),
+                        CharacterText(player),
+                    ));
+                }
+            }
+        });
+}
+
+// Runs every frame since the names only show up once the characters have loaded
+fn update_character_text(
+    settings: Res<MatchSettings>,
+    characters: AvailableCharacters,
+    mut texts: Query<(&mut Text, &CharacterText)>,
+    mut reported_failures: Local<bool>,
+) {
+    if !*reported_failures && characters.all().is_some() {
+        for path in characters.failed() {
+            warn!("Character {} failed to load and is left out", path);
+        }
+        *reported_failures = true;
+    }
+    for (mut text, CharacterText(player)) in &mut texts {
+        let Some(character) = characters.get(settings.characters[*player]) else {
+            continue;
+        };
+        if text.sections[0].value != character.name {
+            text.sections[0].value = character.name.clone();
+        }
+    }
+}
+
+fn binding_label(bindings: &KeyBindings, action: PlayerAction) -> String {
+    match bindings.gamepad.button(action) {
+        Some(button) => format!("{:?} / {:?}", bindings[action], button),
+        None => format!("{:?}", bindings[action]),
+    }
+}
+
 fn set_key_binding_button_highlighting(mut background_color: Mut<BackgroundColor>, selected: bool) {
     if selected {
         background_color.0 = Color::BLUE;
@@ -283,7 +766,7 @@ fn set_key_binding_button_highlighting(mut background_color: Mut<BackgroundColor
 
 fn select_key_binding(
     mut select_key_binding: EventReader<SelectKeyBinding>,
-    mut selectors: Query<(&MenuButtonAction, &mut BackgroundColor, &Children), With<Button>>,
+    mut selectors: Query<(&MenuButtonAction, &mut BackgroundColor), With<Button>>,
     mut menu_state: Query<&mut MenuState>,
 ) {
     let mut menu_state = menu_state
@@ -295,15 +778,15 @@ fn select_key_binding(
         action: select_action,
     } in select_key_binding.read()
     {
-        for (action, background_color, children) in selectors.iter_mut() {
+        // The open conflict has to be answered first
+        if let MenuState::ResolveBindingConflict { .. } = *menu_state {
+            continue;
+        }
+        for (action, background_color) in selectors.iter_mut() {
             if let MenuButtonAction::SelectKeyBinding { player, action } = *action {
                 let selected = (player, action) == (*select_player, *select_action);
                 if selected {
-                    *menu_state = MenuState::SelectKeyBinding {
-                        player,
-                        action,
-                        text_ref: children[0],
-                    };
+                    *menu_state = MenuState::SelectKeyBinding { player, action };
                 }
                 set_key_binding_button_highlighting(background_color, selected);
             }
@@ -311,27 +794,89 @@ fn select_key_binding(
     }
 }
 
+fn apply_binding(bindings: &mut KeyBindings, action: PlayerAction, input: BindingInput) {
+    match input {
+        BindingInput::Key(key) => bindings[action] = key,
+        // Binding any button also picks the gamepad the player uses
+        BindingInput::GamepadButton(button) => {
+            bindings.gamepad.gamepad = button.gamepad;
+            bindings.gamepad.set_button(action, button.button_type);
+        }
+    }
+}
+
+fn input_label(input: BindingInput) -> String {
+    match input {
+        BindingInput::Key(key) => format!("{:?}", key),
+        BindingInput::GamepadButton(button) => format!("{:?}", button.button_type),
+    }
+}
+
+// Another binding, of any player, that already uses the input
+fn find_binding_conflict(
+    controls: &PlayerControls,
+    player: usize,
+    action: PlayerAction,
+    input: BindingInput,
+) -> Option<(usize, PlayerAction)> {
+    // Movement has fixed gamepad buttons, so a button bound to it is never used
+    let movement = controls.controls[player].gamepad.button(action).is_none();
+    if matches!(input, BindingInput::GamepadButton(_)) && movement {
+        return None;
+    }
+    controls
+        .controls
+        .iter()
+        .enumerate()
+        .flat_map(|(other_player, bindings)| {
+            PlayerAction::ALL.map(|other_action| (other_player, other_action, bindings))
+        })
+        .filter(|(other_player, other_action, _)| {
+            (*other_player, *other_action) != (player, action)
+        })
+        .find(|(other_player, other_action, bindings)| match input {
+            BindingInput::Key(key) => bindings[*other_action] == key,
+            // The player's own buttons move over to the new gamepad along with this one
+            BindingInput::GamepadButton(button) => {
+                (*other_player == player || bindings.gamepad.gamepad == button.gamepad)
+                    && bindings.gamepad.button(*other_action) == Some(button.button_type)
+            }
+        })
+        .map(|(other_player, other_action, _)| (other_player, other_action))
+}
+
+fn has_binding_conflict(controls: &PlayerControls, player: usize, action: PlayerAction) -> bool {
+    let bindings = &controls.controls[player];
+    let key = BindingInput::Key(bindings[action]);
+    let button = bindings.gamepad.button(action).map(|button_type| {
+        BindingInput::GamepadButton(GamepadButton::new(bindings.gamepad.gamepad, button_type))
+    });
+    std::iter::once(key)
+        .chain(button)
+        .any(|input| find_binding_conflict(controls, player, action, input).is_some())
+}
+
 fn set_key_binding(
+    mut commands: Commands,
     mut set_key_binding: EventReader<SetKeyBinding>,
-    mut text: Query<&mut Text>,
     mut menu_state: Query<&mut MenuState>,
     mut buttons: Query<(&mut BackgroundColor, &MenuButtonAction), With<Button>>,
     mut controls: ResMut<PlayerControls>,
 ) {
-    for SetKeyBinding { key } in set_key_binding.read() {
+    for SetKeyBinding { input } in set_key_binding.read() {
         let mut menu_state = menu_state.iter_mut().next().expect("Menu state should exist");
-        let MenuState::SelectKeyBinding {
-            player,
-            action,
-            text_ref,
-        } = *menu_state
-        else {
+        let MenuState::SelectKeyBinding { player, action } = *menu_state else {
             return;
         };
-        let mut text = text.get_mut(text_ref).unwrap();
-
-        *text = Text::from_section(format!("{:?}", key), TextStyle::default());
-        controls.controls[player as usize][action] = *key;
+        // Movement always comes from the d-pad and stick, so keep waiting for a key rather
+        // than moving the player over to the gamepad that was pressed
+        let movement = controls.controls[player as usize]
+            .gamepad
+            .button(action)
+            .is_none();
+        if matches!(input, BindingInput::GamepadButton(_)) && movement {
+            continue;
+        }
 
         for (bc, _) in buttons
             .iter_mut()
@@ -340,12 +885,254 @@ fn set_key_binding(
             set_key_binding_button_highlighting(bc, false);
         }
 
+        match find_binding_conflict(&controls, player as usize, action, *input) {
+            Some((other_player, other_action)) => {
+                spawn_binding_conflict_prompt(&mut commands, *input, other_player, other_action);
+                *menu_state = MenuState::ResolveBindingConflict {
+                    player,
+                    action,
+                    input: *input,
+                    other_player,
+                    other_action,
+                };
+            }
+            None => {
+                apply_binding(&mut controls.controls[player as usize], action, *input);
+                *menu_state = MenuState::Default;
+            }
+        }
+    }
+}
+
+fn spawn_binding_conflict_prompt(
+    commands: &mut Commands,
+    input: BindingInput,
+    other_player: usize,
+    other_action: PlayerAction,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            BindingConflictPrompt,
+            MainMenu,
+        ))
+        .with_children(|parrent| {
+            parrent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(10.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    },
+                    background_color: Color::DARK_GRAY.into(),
+                    ..default()
+                })
+                .with_children(|parrent| {
+                    parrent.spawn(TextBundle::from_section(
+                        format!(
+                            "{} is already bound to {:?} for Player {}",
+                            input_label(input),
+                            other_action,
+                            other_player + 1
+                        ),
+                        TextStyle::default(),
+                    ));
+                    parrent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                column_gap: Val::Px(20.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parrent| {
+                            for (label, swap) in [("Swap", true), ("Cancel", false)] {
+                                parrent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: Style {
+                                                width: Val::Px(100.0),
+                                                padding: UiRect::all(Val::Px(5.0)),
+                                                justify_content: JustifyContent::Center,
+                                                ..default()
+                                            },
+                                            background_color: Color::GRAY.into(),
+                                            ..default()
+                                        },
+                                        MenuButtonAction::ResolveBindingConflict { swap },
+                                    ))
+                                    .with_children(|parrent| {
+                                        parrent.spawn(TextBundle::from_section(
+                                            label,
+                                            TextStyle::default(),
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+// Swapping gives the other binding whatever the changed one had before, cancelling changes nothing
+fn resolve_binding_conflict(
+    mut commands: Commands,
+    mut resolve_conflict: EventReader<ResolveBindingConflict>,
+    mut menu_state: Query<&mut MenuState>,
+    prompts: Query<Entity, With<BindingConflictPrompt>>,
+    mut controls: ResMut<PlayerControls>,
+) {
+    for ResolveBindingConflict { swap } in resolve_conflict.read() {
+        let mut menu_state = menu_state
+            .iter_mut()
+            .next()
+            .expect("Menu state should exist");
+        let MenuState::ResolveBindingConflict {
+            player,
+            action,
+            input,
+            other_player,
+            other_action,
+        } = *menu_state
+        else {
+            return;
+        };
+
+        if *swap {
+            let bindings = &mut controls.controls[player as usize];
+            let previous = match input {
+                BindingInput::Key(_) => Some(BindingInput::Key(bindings[action])),
+                BindingInput::GamepadButton(_) => bindings.gamepad.button(action).map(|button| {
+                    BindingInput::GamepadButton(GamepadButton::new(
+                        bindings.gamepad.gamepad,
+                        button,
+                    ))
+                }),
+            };
+            apply_binding(bindings, action, input);
+            match previous {
+                Some(BindingInput::Key(key)) => controls.controls[other_player][other_action] = key,
+                // The other player keeps their own gamepad
+                Some(BindingInput::GamepadButton(button)) => controls.controls[other_player]
+                    .gamepad
+                    .set_button(other_action, button.button_type),
+                None => {}
+            }
+        }
+
+        for prompt in &prompts {
+            commands.entity(prompt).despawn_recursive();
+        }
         *menu_state = MenuState::Default;
     }
 }
 
+fn update_binding_labels(
+    controls: Res<PlayerControls>,
+    mut labels: Query<(&mut Text, Ref<BindingLabel>)>,
+) {
+    for (mut text, label) in &mut labels {
+        if controls.is_changed() || label.is_added() {
+            text.sections[0].value = binding_label(&controls.controls[label.player], label.action);
+            // Conflicting bindings are shown in red until one of them is changed
+            text.sections[0].style.color =
+                if has_binding_conflict(&controls, label.player, label.action) {
+                    Color::RED
+                } else {
+                    Color::WHITE
+                };
+        }
+    }
+}
+
+fn spawn_restore_defaults_button(parrent: &mut ChildBuilder, player: usize) {
+    parrent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    margin: UiRect::top(Val::Px(10.0)),
+                    padding: UiRect::all(Val::Px(5.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::GRAY.into(),
+                ..default()
+            },
+            MenuButtonAction::RestoreDefaultBindings { player },
+        ))
+        .with_children(|parrent| {
+            parrent.spawn(TextBundle::from_section(
+                "Restore defaults",
+                TextStyle::default(),
+            ));
+        });
+}
+
 fn cleanup(to_despawn: Query<Entity, With<MainMenu>>, mut commands: Commands) {
     for entity in &to_despawn {
         commands.entity(entity).despawn_recursive();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding_app(player: u32, action: PlayerAction) -> App {
+        let mut app = App::new();
+        app.init_resource::<PlayerControls>()
+            .add_event::<SetKeyBinding>()
+            .add_systems(Update, set_key_binding);
+        app.world
+            .spawn(MenuState::SelectKeyBinding { player, action });
+        app
+    }
+
+    fn press(app: &mut App, gamepad: usize, button_type: GamepadButtonType) {
+        app.world.send_event(SetKeyBinding {
+            input: BindingInput::GamepadButton(GamepadButton::new(
+                Gamepad::new(gamepad),
+                button_type,
+            )),
+        });
+        app.update();
+    }
+
+    #[test]
+    fn gamepad_button_binds_action_and_gamepad() {
+        let mut app = binding_app(0, PlayerAction::Shoot);
+        press(&mut app, 5, GamepadButtonType::North);
+
+        let bindings = app.world.resource::<PlayerControls>().controls[0];
+        assert_eq!(bindings.gamepad.gamepad, Gamepad::new(5));
+        assert_eq!(bindings.gamepad.shoot, GamepadButtonType::North);
+    }
+
+    #[test]
+    fn gamepad_button_is_ignored_for_movement() {
+        let mut app = binding_app(0, PlayerAction::Left);
+        let before = app.world.resource::<PlayerControls>().controls[0];
+        press(&mut app, 5, GamepadButtonType::North);
+
+        let after = app.world.resource::<PlayerControls>().controls[0];
+        assert!(after == before);
+        // Still waiting for a key
+        let mut states = app.world.query::<&MenuState>();
+        assert!(matches!(
+            states.single(&app.world),
+            MenuState::SelectKeyBinding { .. }
+        ));
+    }
+}
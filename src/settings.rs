@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::{MatchSettings, PlayerControls, MAX_PLAYERS};
+
+// Saves the settings whenever they are changed in the menu
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, save_changed_settings);
+    }
+}
+
+// Everything that is kept between sessions
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SavedSettings {
+    pub controls: PlayerControls,
+    pub match_settings: MatchSettings,
+}
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("Could not access settings file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse settings: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("Could not write settings: {0}")]
+    Write(#[from] ron::Error),
+    #[error("Could not access local storage: {0}")]
+    Storage(String),
+}
+
+// Falls back to the defaults if nothing has been saved yet or the saved settings are unreadable
+pub fn load() -> SavedSettings {
+    let saved = match storage::read() {
+        Ok(Some(text)) => ron::from_str::<SavedSettings>(&text).map_err(SettingsError::from),
+        Ok(None) => return SavedSettings::default(),
+        Err(error) => Err(error),
+    };
+
+    match saved {
+        Ok(mut saved) => {
+            // Every player slot needs bindings, even if fewer were saved
+            let defaults = PlayerControls::default().controls;
+            let saved_count = saved.controls.controls.len();
+            saved.controls.controls.truncate(MAX_PLAYERS);
+            saved
+                .controls
+                .controls
+                .extend(defaults.into_iter().skip(saved_count));
+            // The file may have been edited by hand
+            saved.match_settings.clamp();
+            saved
+        }
+        Err(error) => {
+            warn!("Using default settings: {}", error);
+            SavedSettings::default()
+        }
+    }
+}
+
+fn save(settings: &SavedSettings) -> Result<(), SettingsError> {
+    let text = ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default())?;
+    storage::write(&text)
+}
+
+fn save_changed_settings(controls: Res<PlayerControls>, match_settings: Res<MatchSettings>) {
+    let added = controls.is_added() || match_settings.is_added();
+    let changed = controls.is_changed() || match_settings.is_changed();
+    if added || !changed {
+        return;
+    }
+
+    let settings = SavedSettings {
+        controls: controls.clone(),
+        match_settings: match_settings.clone(),
+    };
+    if let Err(error) = save(&settings) {
+        warn!("Could not save settings: {}", error);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::{fs, io::ErrorKind};
+
+    use super::SettingsError;
+
+    const SETTINGS_FILE: &str = "settings.ron";
+
+    pub fn read() -> Result<Option<String>, SettingsError> {
+        match fs::read_to_string(SETTINGS_FILE) {
+            Ok(text) => Ok(Some(text)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn write(text: &str) -> Result<(), SettingsError> {
+        Ok(fs::write(SETTINGS_FILE, text)?)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use web_sys::Storage;
+
+    use super::SettingsError;
+
+    const STORAGE_KEY: &str = "gamejam2024.settings";
+
+    fn local_storage() -> Result<Storage, SettingsError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| SettingsError::Storage("not available".to_string()))
+    }
+
+    pub fn read() -> Result<Option<String>, SettingsError> {
+        local_storage()?
+            .get_item(STORAGE_KEY)
+            .map_err(|error| SettingsError::Storage(format!("{:?}", error)))
+    }
+
+    pub fn write(text: &str) -> Result<(), SettingsError> {
+        local_storage()?
+            .set_item(STORAGE_KEY, text)
+            .map_err(|error| SettingsError::Storage(format!("{:?}", error)))
+    }
+}